@@ -4,6 +4,21 @@ const COMMANDS: &[&str] = &[
   "send_pin",
   "select_key",
   "cancel",
+  "status",
+  "pin_info",
+  "set_pin",
+  "change_pin",
+  "list_credentials",
+  "delete_credential",
+  "update_credential_user",
+  "fingerprint_sensor_info",
+  "list_enrollments",
+  "enroll_fingerprint",
+  "rename_enrollment",
+  "delete_enrollment",
+  "configure_authenticator",
+  "prepare_reset",
+  "reset_authenticator",
 ];
 
 fn main() {
@@ -11,7 +11,7 @@ use webauthn_rs::{
 };
 use webauthn_rs_proto::{
   PublicKeyCredential, PublicKeyCredentialCreationOptions, PublicKeyCredentialRequestOptions,
-  RegisterPublicKeyCredential,
+  RegisterPublicKeyCredential, ResidentKeyRequirement,
 };
 
 #[tauri::command]
@@ -30,10 +30,16 @@ async fn reg_start(
     .get(uuid)
     .map(|p| p.iter().map(|p| p.cred_id().clone()).collect());
 
-  let (challenge, state_val) = webauthn
+  let (mut challenge, state_val) = webauthn
     .start_passkey_registration(*uuid, name, name, passkey)
     .panic_log("Failed to start registration");
 
+  // request a discoverable credential so the key can also be used with `auth_start`
+  if let Some(selection) = &mut challenge.public_key.authenticator_selection {
+    selection.resident_key = Some(ResidentKeyRequirement::Required);
+    selection.require_resident_key = true;
+  }
+
   let mut state = state.lock().await;
   state.replace((state_val, *uuid));
 
@@ -4,42 +4,112 @@ import type {
   PublicKeyCredentialRequestOptionsJSON,
   RegistrationResponseJSON
 } from '@simplewebauthn/types';
-import { invoke } from '@tauri-apps/api/core';
-import { type UnlistenFn, listen } from '@tauri-apps/api/event';
+import { Channel, invoke } from '@tauri-apps/api/core';
+import type { UnlistenFn } from '@tauri-apps/api/event';
 
 export type * as types from '@simplewebauthn/types';
 
 export type WebauthnEvent =
   | {
-      type: WebauthnEventType.SelectDevice | WebauthnEventType.PresenceRequired;
+      type: WebauthnEventType.Started;
+      kind: OperationKind;
+    }
+  | {
+      type: WebauthnEventType.Finished;
+      outcome: Outcome;
+    }
+  | {
+      type:
+        | WebauthnEventType.SelectDevice
+        | WebauthnEventType.PresenceRequired
+        | WebauthnEventType.ReinsertKey;
     }
   | {
       type: WebauthnEventType.PinEvent;
       event: PinEvent;
+      /** Set if the authenticator waits for a PIN, pass it to {@link sendPin}. */
+      requestId?: number;
     }
   | {
       type: WebauthnEventType.SelectKey;
       keys: AuthKey[];
+      /** Pass it to {@link selectKey}. */
+      requestId: number;
+    }
+  | {
+      type: WebauthnEventType.DeviceAttached | WebauthnEventType.DeviceRemoved;
+      device: DeviceInfo;
+    }
+  | {
+      type: WebauthnEventType.EnrollmentSample;
+      status: SampleStatus;
+      /** Number of samples still required to finish the enrollment. */
+      remainingSamples: number;
     };
 
 export enum WebauthnEventType {
+  Started = 'started',
+  Finished = 'finished',
   SelectDevice = 'selectDevice',
   PresenceRequired = 'presenceRequired',
   PinEvent = 'pinEvent',
-  SelectKey = 'selectKey'
+  SelectKey = 'selectKey',
+  /** Unplug and re-insert the security key, it can only be reset right after it was plugged in. */
+  ReinsertKey = 'reinsertKey',
+  /** A security key was plugged in, only reported on Linux. */
+  DeviceAttached = 'deviceAttached',
+  /** A security key was unplugged, only reported on Linux. */
+  DeviceRemoved = 'deviceRemoved',
+  EnrollmentSample = 'enrollmentSample'
 }
 
+/**
+ * A security key that was plugged in or unplugged during an operation.
+ */
+export interface DeviceInfo {
+  /** Platform specific path of the device, e.g. `/dev/hidraw3`. */
+  path: string;
+  /** The product name reported by the device. */
+  name: string | null;
+}
+
+/**
+ * Quality of a fingerprint sample captured during {@link enrollFingerprint}.
+ */
+export type SampleStatus =
+  | 'good'
+  | 'tooHigh'
+  | 'tooLow'
+  | 'tooLeft'
+  | 'tooRight'
+  | 'tooFast'
+  | 'tooSlow'
+  | 'poorQuality'
+  | 'tooSkewed'
+  | 'tooShort'
+  | 'mergeFailure'
+  | 'exists'
+  | 'noUserActivity'
+  | 'noUserPresenceTransition'
+  | 'other';
+
 export type PinEvent =
   | {
       type:
         | PinEventType.PinRequired
         | PinEventType.PinAuthBlocked
         | PinEventType.PinBlocked
-        | PinEventType.UvBlocked;
+        | PinEventType.UvBlocked
+        | PinEventType.PinTooShort
+        | PinEventType.PinNotSet;
     }
   | {
       type: PinEventType.InvalidPin | PinEventType.InvalidUv;
       attempts_remaining?: number;
+    }
+  | {
+      type: PinEventType.PinTooLong;
+      length: number;
     };
 
 export enum PinEventType {
@@ -48,58 +118,292 @@ export enum PinEventType {
   PinAuthBlocked = 'pinAuthBlocked',
   PinBlocked = 'pinBlocked',
   InvalidUv = 'invalidUv',
-  UvBlocked = 'uvBlocked'
+  UvBlocked = 'uvBlocked',
+  PinTooShort = 'pinTooShort',
+  PinTooLong = 'pinTooLong',
+  PinNotSet = 'pinNotSet'
 }
 
+/**
+ * How a ceremony has ended, the error of a failed ceremony is thrown by {@link register} or {@link authenticate}.
+ */
+export type Outcome = 'completed' | 'cancelled' | 'timedOut' | 'failed';
+
 export interface AuthKey {
   id: string;
   name?: string;
   displayName?: string;
 }
 
+/**
+ * Inputs or results of the `prf` extension, base64url encoded.
+ */
+export interface PrfValues {
+  first: string;
+  second?: string;
+}
+
+/**
+ * Inputs of the `prf` extension, passed as `extensions.prf` of the options.
+ */
+export interface PrfInputs {
+  eval?: PrfValues;
+  /** Inputs for specific credentials, keyed by the base64url encoded credential id. Only allowed during authentication. */
+  evalByCredential?: Record<string, PrfValues>;
+}
+
+/**
+ * Outputs of the `prf` extension, returned as `extensions.prf` of the response.
+ */
+export interface PrfOutputs {
+  /** Whether the new credential supports the extension, only set by {@link register}. */
+  enabled?: boolean;
+  /** The evaluated PRF, only set by {@link authenticate}. */
+  results?: PrfValues;
+}
+
+/**
+ * Extension inputs handled by the plugin in addition to those of `@simplewebauthn/types`.
+ * Binary values are base64url encoded.
+ */
+export interface ExtensionInputs {
+  prf?: PrfInputs;
+}
+
+/**
+ * Extension outputs returned by the plugin in addition to those of `@simplewebauthn/types`.
+ */
+export interface ExtensionOutputs {
+  prf?: PrfOutputs;
+  credProtect?:
+    | 'userVerificationOptional'
+    | 'userVerificationOptionalWithCredentialIDList'
+    | 'userVerificationRequired';
+  minPinLength?: number;
+}
+
+/**
+ * Name of the `DOMException` a browser would throw for the same failure.
+ */
+export type WebauthnErrorName =
+  | 'NotAllowedError'
+  | 'InvalidStateError'
+  | 'SecurityError'
+  | 'AbortError'
+  | 'NotSupportedError'
+  | 'TimeoutError'
+  | 'ConstraintError'
+  | 'SyntaxError'
+  | 'UnknownError';
+
+/**
+ * The error the promises of this plugin reject with.
+ */
+export interface WebauthnError {
+  name: WebauthnErrorName;
+  /** A stable, machine-readable code, e.g. `noToken` or `credentialExcluded`. */
+  code: string;
+  message: string;
+  /** Backend specific details, e.g. the CTAP2 status. */
+  detail: string | null;
+}
+
+export type OperationKind = 'register' | 'authenticate' | 'manage';
+
+/**
+ * PIN state of a security key as returned by {@link pinInfo}.
+ */
+export interface PinInfo {
+  supported: boolean;
+  isSet: boolean;
+  /** Minimum number of characters of a new PIN. */
+  minLength: number;
+  /** Whether the PIN has to be changed before the key can be used. */
+  forceChange: boolean;
+  /** Whether the key has built-in user verification, e.g. a fingerprint sensor. */
+  uvSupported: boolean;
+  uvConfigured: boolean;
+  /**
+   * Remaining PIN attempts. Always `null` for now, the `authenticator` crate can't query them.
+   * The remaining attempts are reported by the `invalidPin` event instead.
+   */
+  pinRetries: number | null;
+  /**
+   * Remaining attempts of the built-in user verification. Always `null` for now, like {@link PinInfo.pinRetries}.
+   * The remaining attempts are reported by the `invalidUv` event instead.
+   */
+  uvRetries: number | null;
+}
+
+/**
+ * The state of the authenticator as returned by {@link status}.
+ * `awaitingPin` and `awaitingSelection` are only reported on Linux and macOS.
+ */
+export type OperationState =
+  | { state: 'idle' }
+  | {
+      state: 'inProgress' | 'awaitingPin' | 'awaitingSelection';
+      id: number;
+      kind: OperationKind;
+    };
+
+/**
+ * Discoverable credentials stored on a security key as returned by {@link listCredentials}.
+ */
+export interface CredentialList {
+  /** Number of discoverable credentials stored on the key. */
+  existing: number;
+  /** Number of additional discoverable credentials the key can store. */
+  remaining: number;
+  relyingParties: RelyingPartyCredentials[];
+}
+
+export interface RelyingPartyCredentials {
+  rpId: string;
+  rpName: string | null;
+  credentials: StoredCredential[];
+}
+
+export interface StoredCredential {
+  /** Base64url encoded credential id. */
+  id: string;
+  user: CredentialUser;
+  /** `null` if the key reports an unknown policy. */
+  credProtect:
+    | 'userVerificationOptional'
+    | 'userVerificationOptionalWithCredentialIDList'
+    | 'userVerificationRequired'
+    | null;
+}
+
+export interface CredentialUser {
+  /** Base64url encoded user handle. */
+  id: string;
+  name: string | null;
+  displayName: string | null;
+}
+
+/**
+ * The fingerprint sensor of a security key as returned by {@link fingerprintSensorInfo}.
+ */
+export interface FingerprintSensorInfo {
+  kind: 'touch' | 'swipe' | 'other';
+  /** Maximum number of samples needed for an enrollment. */
+  maxSamples: number;
+  /** Maximum length of an enrollment name in bytes. */
+  maxNameLength: number | null;
+}
+
+/**
+ * A fingerprint enrolled on a security key.
+ */
+export interface Enrollment {
+  /** Base64url encoded template id. */
+  id: string;
+  name: string | null;
+}
+
+/**
+ * A configuration change for {@link configureAuthenticator}.
+ */
+export type ConfigChange =
+  | { type: 'enableEnterpriseAttestation' }
+  | { type: 'toggleAlwaysUv' }
+  | {
+      type: 'setMinPinLength';
+      /** The new minimum length, it can only be increased. */
+      minPinLength?: number;
+      /** Relying parties allowed to read the minimum length with the `minPinLength` extension. */
+      rpIds?: string[];
+      /** Require a new PIN before the key can be used again. */
+      forceChangePin?: boolean;
+    };
+
+/**
+ * The configuration of a security key as returned by {@link configureAuthenticator}.
+ */
+export interface AuthenticatorSettings {
+  alwaysUv: boolean;
+  enterpriseAttestation: boolean;
+  minPinLength: number;
+  forcePinChange: boolean;
+}
+
 export const EVENT_NAME = 'tauri-plugin-webauthn';
 
+const listeners = new Set<(event: WebauthnEvent) => void>();
+
+/**
+ * Every operation gets its own channel, the plugin does not emit its events anywhere else.
+ */
+const eventChannel = (
+  onEvent?: (event: WebauthnEvent) => void
+): Channel<WebauthnEvent> => {
+  const channel = new Channel<WebauthnEvent>();
+  channel.onmessage = (event) => {
+    onEvent?.(event);
+    listeners.forEach((listener) => listener(event));
+  };
+  return channel;
+};
+
 /**
  * Tries to register using the native WebAuthn API.
  *
  * @param origin The origin of the request. This is used to verify the request.
+ *   Ignored if the plugin is configured to derive the origin from the webview.
  * @param options The webauthn options. This is used to create the request.
+ * @param onEvent Receives the events of this registration on a private channel.
+ *   The events are also passed to the listeners added with {@link registerListener}.
  * @returns A promise that resolves to the registration response.
+ * @throws {WebauthnError} If the registration failed.
  */
 export const register = async (
   origin: string,
-  options: PublicKeyCredentialCreationOptionsJSON
+  options: PublicKeyCredentialCreationOptionsJSON,
+  onEvent?: (event: WebauthnEvent) => void
 ): Promise<RegistrationResponseJSON> =>
   await invoke<RegistrationResponseJSON>('plugin:webauthn|register', {
     options,
-    origin
+    origin,
+    onEvent: eventChannel(onEvent)
   });
 
 /**
  * Tries to authenticate using the native WebAuthn API.
  *
  * @param origin The origin of the request. This is used to verify the request.
+ *   Ignored if the plugin is configured to derive the origin from the webview.
  * @param options The webauthn options. This is used to create the request.
+ * @param onEvent Receives the events of this authentication on a private channel.
+ *   The events are also passed to the listeners added with {@link registerListener}.
  * @returns A promise that resolves to the authentication response.
+ * @throws {WebauthnError} If the authentication failed.
  */
 export const authenticate = async (
   origin: string,
-  options: PublicKeyCredentialRequestOptionsJSON
+  options: PublicKeyCredentialRequestOptionsJSON,
+  onEvent?: (event: WebauthnEvent) => void
 ): Promise<PublicKeyCredentialJSON> =>
   await invoke<PublicKeyCredentialJSON>('plugin:webauthn|authenticate', {
     options,
-    origin
+    origin,
+    onEvent: eventChannel(onEvent)
   });
 
+
 /**
  * Sends a pin to the authenticator.
  * Does nothing on windows and mobile.
  *
+ * @param requestId The `requestId` of the `pinEvent` that requested the pin.
  * @param pin The pin to send to the authenticator.
  * @returns A promise that resolves when the pin has been sent.
+ * @throws {WebauthnError} If the request is no longer pending or was started by another webview.
  */
-export const sendPin = async (pin: string): Promise<void> =>
+export const sendPin = async (requestId: number, pin: string): Promise<void> =>
   await invoke('plugin:webauthn|send_pin', {
+    requestId,
     pin
   });
 
@@ -107,11 +411,17 @@ export const sendPin = async (pin: string): Promise<void> =>
  * Select a key from the list of keys received by the `selectKey` event.
  * Does nothing on windows and mobile.
  *
- * @param uv The uv to send to the authenticator.
- * @returns A promise that resolves when the uv has been sent.
+ * @param requestId The `requestId` of the `selectKey` event.
+ * @param index The index of the selected key in the list of keys.
+ * @returns A promise that resolves when the key has been selected.
+ * @throws {WebauthnError} If the request is no longer pending or was started by another webview.
  */
-export const selectKey = async (index: number): Promise<void> =>
+export const selectKey = async (
+  requestId: number,
+  index: number
+): Promise<void> =>
   await invoke('plugin:webauthn|select_key', {
+    requestId,
     key: index
   });
 
@@ -120,12 +430,262 @@ export const selectKey = async (index: number): Promise<void> =>
  * Does nothing on windows and mobile.
  *
  * @returns A promise that resolves when the operation has been cancelled.
+ * @throws {WebauthnError} If the operation was started by another webview.
  */
 export const cancel = async (): Promise<void> =>
   await invoke('plugin:webauthn|cancel');
 
 /**
- * Creates a listener for the webauthn events.
+ * Returns the state of the current operation.
+ *
+ * @returns A promise that resolves to the current state.
+ */
+export const status = async (): Promise<OperationState> =>
+  await invoke<OperationState>('plugin:webauthn|status');
+
+/**
+ * Reads the PIN state of a security key. The user has to select the key by touching it.
+ * Only supported on Linux and macOS.
+ *
+ * @param onEvent Receives the events of this operation on a private channel.
+ * @returns A promise that resolves to the PIN state.
+ * @throws {WebauthnError} `NotSupportedError` on other platforms.
+ */
+export const pinInfo = async (
+  onEvent?: (event: WebauthnEvent) => void
+): Promise<PinInfo> =>
+  await invoke<PinInfo>('plugin:webauthn|pin_info', {
+    onEvent: eventChannel(onEvent)
+  });
+
+/**
+ * Sets the PIN of a security key which does not have one yet.
+ * Only supported on Linux and macOS.
+ *
+ * @param pin The new PIN, it has to be at least {@link PinInfo.minLength} characters and at most 63 bytes long.
+ * @param onEvent Receives the events of this operation on a private channel.
+ * @throws {WebauthnError} `InvalidStateError` if the key already has a PIN, `ConstraintError` if the PIN violates the policy of the key.
+ */
+export const setPin = async (
+  pin: string,
+  onEvent?: (event: WebauthnEvent) => void
+): Promise<void> =>
+  await invoke('plugin:webauthn|set_pin', {
+    pin,
+    onEvent: eventChannel(onEvent)
+  });
+
+/**
+ * Changes the PIN of a security key.
+ * If the current PIN is wrong, a `pinEvent` requests it again.
+ * Only supported on Linux and macOS.
+ *
+ * @param currentPin The current PIN.
+ * @param newPin The new PIN, it has to be at least {@link PinInfo.minLength} characters and at most 63 bytes long.
+ * @param onEvent Receives the events of this operation on a private channel.
+ * @throws {WebauthnError} `InvalidStateError` if the key has no PIN, `ConstraintError` if the PIN violates the policy of the key.
+ */
+export const changePin = async (
+  currentPin: string,
+  newPin: string,
+  onEvent?: (event: WebauthnEvent) => void
+): Promise<void> =>
+  await invoke('plugin:webauthn|change_pin', {
+    currentPin,
+    newPin,
+    onEvent: eventChannel(onEvent)
+  });
+
+/**
+ * Lists the discoverable credentials (passkeys) stored on a security key.
+ * The user is asked for the PIN through a `pinEvent`.
+ * Only supported on Linux and macOS.
+ *
+ * @param onEvent Receives the events of this operation on a private channel.
+ * @returns A promise that resolves to the credentials grouped by relying party.
+ * @throws {WebauthnError} `NotSupportedError` if the key or platform does not support credential management.
+ */
+export const listCredentials = async (
+  onEvent?: (event: WebauthnEvent) => void
+): Promise<CredentialList> =>
+  await invoke<CredentialList>('plugin:webauthn|list_credentials', {
+    onEvent: eventChannel(onEvent)
+  });
+
+/**
+ * Deletes a discoverable credential from a security key.
+ * The user is asked for the PIN through a `pinEvent`.
+ * Only supported on Linux and macOS.
+ *
+ * @param credentialId The base64url encoded id of the credential.
+ * @param onEvent Receives the events of this operation on a private channel.
+ * @throws {WebauthnError} `NotSupportedError` if the key or platform does not support credential management.
+ */
+export const deleteCredential = async (
+  credentialId: string,
+  onEvent?: (event: WebauthnEvent) => void
+): Promise<void> =>
+  await invoke('plugin:webauthn|delete_credential', {
+    credentialId,
+    onEvent: eventChannel(onEvent)
+  });
+
+/**
+ * Replaces the user name and display name of a discoverable credential.
+ * The user id has to stay the same. The user is asked for the PIN through a `pinEvent`.
+ * Only supported on Linux and macOS.
+ *
+ * @param credentialId The base64url encoded id of the credential.
+ * @param user The new user information.
+ * @param onEvent Receives the events of this operation on a private channel.
+ * @throws {WebauthnError} `NotSupportedError` if the key or platform does not support updating credentials.
+ */
+export const updateCredentialUser = async (
+  credentialId: string,
+  user: CredentialUser,
+  onEvent?: (event: WebauthnEvent) => void
+): Promise<void> =>
+  await invoke('plugin:webauthn|update_credential_user', {
+    credentialId,
+    user,
+    onEvent: eventChannel(onEvent)
+  });
+
+/**
+ * Reads the fingerprint sensor information of a security key.
+ * Only supported on Linux and macOS.
+ *
+ * @param onEvent Receives the events of this operation on a private channel.
+ * @returns A promise that resolves to the sensor information.
+ * @throws {WebauthnError} `NotSupportedError` if the key or platform does not support bio enrollment.
+ */
+export const fingerprintSensorInfo = async (
+  onEvent?: (event: WebauthnEvent) => void
+): Promise<FingerprintSensorInfo> =>
+  await invoke<FingerprintSensorInfo>(
+    'plugin:webauthn|fingerprint_sensor_info',
+    { onEvent: eventChannel(onEvent) }
+  );
+
+/**
+ * Lists the fingerprints enrolled on a security key.
+ * Only supported on Linux and macOS.
+ *
+ * @param onEvent Receives the events of this operation on a private channel.
+ * @returns A promise that resolves to the enrolled fingerprints.
+ * @throws {WebauthnError} `NotSupportedError` if the key or platform does not support bio enrollment.
+ */
+export const listEnrollments = async (
+  onEvent?: (event: WebauthnEvent) => void
+): Promise<Enrollment[]> =>
+  await invoke<Enrollment[]>('plugin:webauthn|list_enrollments', {
+    onEvent: eventChannel(onEvent)
+  });
+
+/**
+ * Enrolls a new fingerprint. The user has to touch the sensor repeatedly,
+ * every sample is reported with an `enrollmentSample` event.
+ * Only supported on Linux and macOS.
+ *
+ * @param name An optional name for the fingerprint.
+ * @param onEvent Receives the events of this operation on a private channel.
+ * @throws {WebauthnError} `NotSupportedError` if the key or platform does not support bio enrollment.
+ */
+export const enrollFingerprint = async (
+  name?: string,
+  onEvent?: (event: WebauthnEvent) => void
+): Promise<void> =>
+  await invoke('plugin:webauthn|enroll_fingerprint', {
+    name,
+    onEvent: eventChannel(onEvent)
+  });
+
+/**
+ * Renames an enrolled fingerprint.
+ * Only supported on Linux and macOS.
+ *
+ * @param id The base64url encoded template id.
+ * @param name The new name.
+ * @param onEvent Receives the events of this operation on a private channel.
+ */
+export const renameEnrollment = async (
+  id: string,
+  name: string,
+  onEvent?: (event: WebauthnEvent) => void
+): Promise<void> =>
+  await invoke('plugin:webauthn|rename_enrollment', {
+    id,
+    name,
+    onEvent: eventChannel(onEvent)
+  });
+
+/**
+ * Removes an enrolled fingerprint.
+ * Only supported on Linux and macOS.
+ *
+ * @param id The base64url encoded template id.
+ * @param onEvent Receives the events of this operation on a private channel.
+ */
+export const deleteEnrollment = async (
+  id: string,
+  onEvent?: (event: WebauthnEvent) => void
+): Promise<void> =>
+  await invoke('plugin:webauthn|delete_enrollment', {
+    id,
+    onEvent: eventChannel(onEvent)
+  });
+
+/**
+ * Changes the configuration of a security key supporting CTAP 2.1 `authenticatorConfig`.
+ * Requires the `webauthn:allow-authenticator-config` permission, it is not granted by default.
+ * Only supported on Linux and macOS.
+ *
+ * @param change The setting to change.
+ * @param onEvent Receives the events of this operation on a private channel.
+ * @throws {WebauthnError} `NotSupportedError` if the key or platform does not support configuration.
+ */
+export const configureAuthenticator = async (
+  change: ConfigChange,
+  onEvent?: (event: WebauthnEvent) => void
+): Promise<AuthenticatorSettings> =>
+  await invoke('plugin:webauthn|configure_authenticator', {
+    change,
+    onEvent: eventChannel(onEvent)
+  });
+
+/**
+ * Issues the confirmation token for {@link resetAuthenticator}.
+ * The token is valid for 60 seconds and can only be used once, by the webview which requested it.
+ * Requires the `webauthn:allow-reset` permission, it is not granted by default.
+ * Only supported on Linux and macOS.
+ */
+export const prepareReset = async (): Promise<string> =>
+  await invoke('plugin:webauthn|prepare_reset');
+
+/**
+ * Resets a security key to its factory state, deleting all passkeys, fingerprints and the PIN.
+ * A `reinsertKey` event asks the user to unplug the key and plug it in again, afterwards the user has to touch it.
+ * On Linux the reset is only sent once the key was plugged in again, on macOS it is sent right away.
+ * Requires the `webauthn:allow-reset` permission, it is not granted by default.
+ * Only supported on Linux and macOS.
+ *
+ * @param confirmation The token returned by {@link prepareReset}.
+ * @param onEvent Receives the events of this operation on a private channel.
+ * @throws {WebauthnError} `NotAllowedError` if the token is invalid (`resetNotConfirmed`),
+ * the key wasn't plugged in again before the timeout (`notAllowed`) or was plugged in too long ago (`resetWindowExpired`).
+ */
+export const resetAuthenticator = async (
+  confirmation: string,
+  onEvent?: (event: WebauthnEvent) => void
+): Promise<void> =>
+  await invoke('plugin:webauthn|reset_authenticator', {
+    confirmation,
+    onEvent: eventChannel(onEvent)
+  });
+
+/**
+ * Adds a listener for the events of all operations started from this page.
+ * The events are delivered on the private channel of each operation, other webviews never receive them.
  * No events are triggered on windows and mobile.
  *
  * @param listener The listener to call when the event is triggered.
@@ -133,7 +693,9 @@ export const cancel = async (): Promise<void> =>
  */
 export const registerListener = async (
   listener: (event: WebauthnEvent) => void
-): Promise<UnlistenFn> =>
-  listen(EVENT_NAME, (event) => {
-    listener(event.payload as WebauthnEvent); // oxlint-disable-line no-unsafe-type-assertion
-  });
+): Promise<UnlistenFn> => {
+  listeners.add(listener);
+  return () => {
+    listeners.delete(listener);
+  };
+};
@@ -0,0 +1,267 @@
+use serde::{Deserialize, Serialize};
+use serde_cbor_2::{Deserializer, Value};
+
+use crate::{Error, Result};
+
+/// The authenticator data signed by the authenticator, e.g. to read the flags of a ceremony result.
+/// See <https://www.w3.org/TR/webauthn-3/#sctn-authenticator-data>.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthenticatorData {
+  pub rp_id_hash: [u8; 32],
+  /// The `AT` and `ED` flags are derived from the presence of the credential data and extensions when encoding.
+  pub flags: u8,
+  pub sign_count: u32,
+  pub attested_credential_data: Option<AttestedCredentialData>,
+  /// The CBOR encoded map of extension outputs.
+  pub extensions: Option<Vec<u8>>,
+}
+
+/// The new credential, only present in the authenticator data of a registration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttestedCredentialData {
+  pub aaguid: [u8; 16],
+  pub credential_id: Vec<u8>,
+  /// The CBOR encoded COSE_Key.
+  pub credential_public_key: Vec<u8>,
+}
+
+#[derive(Serialize)]
+struct AttestationObject<'a> {
+  fmt: &'a str,
+  #[serde(rename = "attStmt")]
+  att_stmt: &'a Value,
+  #[serde(rename = "authData")]
+  auth_data: Value,
+}
+
+impl AuthenticatorData {
+  pub const USER_PRESENT: u8 = 0x01;
+  pub const USER_VERIFIED: u8 = 0x04;
+  pub const ATTESTED: u8 = 0x40;
+  pub const EXTENSION_DATA: u8 = 0x80;
+
+  /// Authenticator data without credential data and extensions.
+  pub fn new(rp_id: &str, flags: u8, sign_count: u32) -> Self {
+    AuthenticatorData {
+      rp_id_hash: openssl::sha::sha256(rp_id.as_bytes()),
+      flags,
+      sign_count,
+      attested_credential_data: None,
+      extensions: None,
+    }
+  }
+
+  /// The raw bytes, as passed to the relying party and signed by the authenticator.
+  /// Fails if the credential id is too long for its 16 bit length.
+  pub fn to_bytes(&self) -> Result<Vec<u8>> {
+    let mut flags = self.flags & !(Self::ATTESTED | Self::EXTENSION_DATA);
+    if self.attested_credential_data.is_some() {
+      flags |= Self::ATTESTED;
+    }
+    if self.extensions.is_some() {
+      flags |= Self::EXTENSION_DATA;
+    }
+
+    let mut data = Vec::with_capacity(37);
+    data.extend(self.rp_id_hash);
+    data.push(flags);
+    data.extend(self.sign_count.to_be_bytes());
+    if let Some(credential) = &self.attested_credential_data {
+      data.extend(credential.aaguid);
+      let length =
+        u16::try_from(credential.credential_id.len()).map_err(|_| Error::InvalidResponse)?;
+      data.extend(length.to_be_bytes());
+      data.extend(&credential.credential_id);
+      data.extend(&credential.credential_public_key);
+    }
+    if let Some(extensions) = &self.extensions {
+      data.extend(extensions);
+    }
+    Ok(data)
+  }
+
+  /// Parses raw authenticator data, the COSE key and extensions have to be CBOR maps.
+  pub fn from_bytes(data: &[u8]) -> Result<Self> {
+    let (rp_id_hash, data) = split::<32>(data)?;
+    let ([flags], data) = split::<1>(data)?;
+    let (sign_count, mut data) = split::<4>(data)?;
+
+    let attested_credential_data = if flags & Self::ATTESTED != 0 {
+      let (aaguid, rest) = split::<16>(data)?;
+      let (length, rest) = split::<2>(rest)?;
+      let length = u16::from_be_bytes(length) as usize;
+      if rest.len() < length {
+        return Err(Error::InvalidResponse);
+      }
+      let (credential_id, rest) = rest.split_at(length);
+      let (credential_public_key, rest) = split_map(rest)?;
+      data = rest;
+      Some(AttestedCredentialData {
+        aaguid,
+        credential_id: credential_id.to_vec(),
+        credential_public_key: credential_public_key.to_vec(),
+      })
+    } else {
+      None
+    };
+
+    let extensions = if flags & Self::EXTENSION_DATA != 0 {
+      let (extensions, rest) = split_map(data)?;
+      data = rest;
+      Some(extensions.to_vec())
+    } else {
+      None
+    };
+
+    if !data.is_empty() {
+      return Err(Error::InvalidResponse);
+    }
+    Ok(AuthenticatorData {
+      rp_id_hash,
+      flags,
+      sign_count: u32::from_be_bytes(sign_count),
+      attested_credential_data,
+      extensions,
+    })
+  }
+
+  /// Encodes the attestation object of a registration.
+  /// See <https://www.w3.org/TR/webauthn-3/#attestation-object>.
+  pub fn attestation_object(&self, fmt: &str, att_stmt: &Value) -> Result<Vec<u8>> {
+    // the fields are in CTAP2 canonical order
+    Ok(serde_cbor_2::to_vec(&AttestationObject {
+      fmt,
+      att_stmt,
+      auth_data: Value::Bytes(self.to_bytes()?),
+    })?)
+  }
+}
+
+fn split<const N: usize>(data: &[u8]) -> Result<([u8; N], &[u8])> {
+  if data.len() < N {
+    return Err(Error::InvalidResponse);
+  }
+  let (head, rest) = data.split_at(N);
+  Ok((head.try_into().unwrap(), rest))
+}
+
+/// Splits off the CBOR map at the start of `data`.
+fn split_map(data: &[u8]) -> Result<(&[u8], &[u8])> {
+  let mut deserializer = Deserializer::from_slice(data);
+  match Value::deserialize(&mut deserializer) {
+    Ok(Value::Map(_)) => Ok(data.split_at(deserializer.byte_offset())),
+    _ => Err(Error::InvalidResponse),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Authenticator data of the authenticatorMakeCredential example in the CTAP2 spec,
+  /// with the `hmac-secret` extension output.
+  const MAKE_CREDENTIAL: [u8; 162] = [
+    0xc2, 0x89, 0xc5, 0xca, 0x9b, 0x04, 0x60, 0xf9, 0x34, 0x6a, 0xb4, 0xe4, 0x2d, 0x84, 0x27, 0x43,
+    0x40, 0x4d, 0x31, 0xf4, 0x84, 0x68, 0x25, 0xa6, 0xd0, 0x65, 0xbe, 0x59, 0x7a, 0x87, 0x05, 0x1d,
+    0xc1, 0x00, 0x00, 0x00, 0x0b, 0xf8, 0xa0, 0x11, 0xf3, 0x8c, 0x0a, 0x4d, 0x15, 0x80, 0x06, 0x17,
+    0x11, 0x1f, 0x9e, 0xdc, 0x7d, 0x00, 0x10, 0x89, 0x59, 0xce, 0xad, 0x5b, 0x5c, 0x48, 0x16, 0x4e,
+    0x8a, 0xbc, 0xd6, 0xd9, 0x43, 0x5c, 0x6f, 0xa5, 0x01, 0x02, 0x03, 0x26, 0x20, 0x01, 0x21, 0x58,
+    0x20, 0xa5, 0xfd, 0x5c, 0xe1, 0xb1, 0xc4, 0x58, 0xc5, 0x30, 0xa5, 0x4f, 0xa6, 0x1b, 0x31, 0xbf,
+    0x6b, 0x04, 0xbe, 0x8b, 0x97, 0xaf, 0xde, 0x54, 0xdd, 0x8c, 0xbb, 0x69, 0x27, 0x5a, 0x8a, 0x1b,
+    0xe1, 0x22, 0x58, 0x20, 0xfa, 0x3a, 0x32, 0x31, 0xdd, 0x9d, 0xee, 0xd9, 0xd1, 0x89, 0x7b, 0xe5,
+    0xa6, 0x22, 0x8c, 0x59, 0x50, 0x1e, 0x4b, 0xcd, 0x12, 0x97, 0x5d, 0x3d, 0xff, 0x73, 0x0f, 0x01,
+    0x27, 0x8e, 0xa6, 0x1c, 0xa1, 0x6b, 0x68, 0x6d, 0x61, 0x63, 0x2d, 0x73, 0x65, 0x63, 0x72, 0x65,
+    0x74, 0xf5,
+  ];
+
+  /// Authenticator data of the authenticatorGetAssertion example in the CTAP2 spec.
+  const GET_ASSERTION: [u8; 37] = [
+    0x62, 0x5d, 0xda, 0xdf, 0x74, 0x3f, 0x57, 0x27, 0xe6, 0x6b, 0xba, 0x8c, 0x2e, 0x38, 0x79, 0x22,
+    0xd1, 0xaf, 0x43, 0xc5, 0x03, 0xd9, 0x11, 0x4a, 0x8f, 0xba, 0x10, 0x4d, 0x84, 0xd0, 0x2b, 0xfa,
+    0x01, 0x00, 0x00, 0x00, 0x11,
+  ];
+
+  #[test]
+  fn make_credential_round_trip() {
+    let auth_data = AuthenticatorData::from_bytes(&MAKE_CREDENTIAL).unwrap();
+    assert_eq!(
+      auth_data.flags,
+      AuthenticatorData::USER_PRESENT
+        | AuthenticatorData::ATTESTED
+        | AuthenticatorData::EXTENSION_DATA
+    );
+    assert_eq!(auth_data.sign_count, 11);
+    let credential = auth_data.attested_credential_data.as_ref().unwrap();
+    assert_eq!(credential.aaguid, MAKE_CREDENTIAL[37..53]);
+    assert_eq!(credential.credential_id, MAKE_CREDENTIAL[55..71]);
+    assert_eq!(credential.credential_public_key, MAKE_CREDENTIAL[71..148]);
+    assert_eq!(
+      auth_data.extensions.as_deref(),
+      Some(&MAKE_CREDENTIAL[148..])
+    );
+    assert_eq!(auth_data.to_bytes().unwrap(), MAKE_CREDENTIAL);
+  }
+
+  #[test]
+  fn get_assertion_round_trip() {
+    let auth_data = AuthenticatorData::from_bytes(&GET_ASSERTION).unwrap();
+    assert_eq!(auth_data.rp_id_hash, GET_ASSERTION[..32]);
+    assert_eq!(auth_data.flags, AuthenticatorData::USER_PRESENT);
+    assert_eq!(auth_data.sign_count, 17);
+    assert_eq!(auth_data.attested_credential_data, None);
+    assert_eq!(auth_data.extensions, None);
+    assert_eq!(auth_data.to_bytes().unwrap(), GET_ASSERTION);
+  }
+
+  #[test]
+  fn long_extensions_round_trip() {
+    // longer than 255 bytes, so a CBOR byte string of the authenticator data has a three byte header
+    let mut extensions = vec![0xa1, 0x68];
+    extensions.extend(b"credBlob");
+    extensions.extend([0x59, 0x01, 0x00]);
+    extensions.extend([0x2a; 256]);
+
+    let mut auth_data = AuthenticatorData::new(
+      "example.com",
+      AuthenticatorData::USER_PRESENT | AuthenticatorData::USER_VERIFIED,
+      1,
+    );
+    auth_data.extensions = Some(extensions);
+    let bytes = auth_data.to_bytes().unwrap();
+    assert_eq!(
+      bytes[32],
+      AuthenticatorData::USER_PRESENT
+        | AuthenticatorData::USER_VERIFIED
+        | AuthenticatorData::EXTENSION_DATA
+    );
+
+    let parsed = AuthenticatorData::from_bytes(&bytes).unwrap();
+    assert_eq!(parsed.extensions, auth_data.extensions);
+    assert_eq!(parsed.to_bytes().unwrap(), bytes);
+  }
+
+  #[test]
+  fn rejects_malformed_data() {
+    assert!(AuthenticatorData::from_bytes(&GET_ASSERTION[..36]).is_err());
+    assert!(AuthenticatorData::from_bytes(&MAKE_CREDENTIAL[..147]).is_err());
+    // trailing bytes after the authenticator data
+    let mut trailing = GET_ASSERTION.to_vec();
+    trailing.push(0);
+    assert!(AuthenticatorData::from_bytes(&trailing).is_err());
+    // extension flag without extensions
+    let mut flags = GET_ASSERTION;
+    flags[32] |= AuthenticatorData::EXTENSION_DATA;
+    assert!(AuthenticatorData::from_bytes(&flags).is_err());
+  }
+
+  #[test]
+  fn rejects_long_credential_id() {
+    let mut auth_data = AuthenticatorData::from_bytes(&MAKE_CREDENTIAL).unwrap();
+    let credential = auth_data.attested_credential_data.as_mut().unwrap();
+    credential.credential_id = vec![0; u16::MAX as usize];
+    assert!(auth_data.to_bytes().is_ok());
+    let credential = auth_data.attested_credential_data.as_mut().unwrap();
+    credential.credential_id.push(0);
+    assert!(auth_data.to_bytes().is_err());
+  }
+}
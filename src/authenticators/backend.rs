@@ -0,0 +1,255 @@
+use tauri::{plugin::PluginApi, AppHandle, Runtime, Url};
+use webauthn_rs_proto::{
+  PublicKeyCredential, PublicKeyCredentialCreationOptions, PublicKeyCredentialRequestOptions,
+  RegisterPublicKeyCredential,
+};
+
+use super::{virtual_authenticator, Authenticator, Platform};
+use crate::{
+  config::Backend,
+  events::EventSink,
+  extensions::{ExtensionInputs, ExtensionOutputs, WithExtensions},
+  AuthenticatorSettings, Config, ConfigChange, CredentialList, CredentialUser, Enrollment,
+  FingerprintSensorInfo, PinInfo, VirtualAuthenticatorOptions,
+};
+
+/// Forwards a method call to the selected backend, awaiting it if the call ends with `.await`.
+macro_rules! dispatch {
+  ($self:ident.$method:ident($($arg:ident),*)) => {
+    match $self {
+      Webauthn::Platform(webauthn) => webauthn.$method($($arg),*),
+      Webauthn::Virtual(webauthn) => webauthn.$method($($arg),*),
+    }
+  };
+  ($self:ident.$method:ident($($arg:ident),*).await) => {
+    match $self {
+      Webauthn::Platform(webauthn) => webauthn.$method($($arg),*).await,
+      Webauthn::Virtual(webauthn) => webauthn.$method($($arg),*).await,
+    }
+  };
+}
+
+/// Dispatches to the backend selected in the config.
+pub enum Webauthn<R: Runtime> {
+  Platform(Platform<R>),
+  Virtual(virtual_authenticator::Webauthn<R>),
+}
+
+impl<R: Runtime> Webauthn<R> {
+  /// Replaces the options of the virtual authenticator.
+  /// Does nothing if the platform backend is used.
+  pub fn set_options(&self, options: VirtualAuthenticatorOptions) {
+    match self {
+      Webauthn::Virtual(webauthn) => webauthn.set_options(options),
+      Webauthn::Platform(_) => {
+        #[cfg(feature = "log")]
+        log::warn!("set_options is only supported by the virtual authenticator");
+      }
+    }
+  }
+
+  /// Removes all credentials stored in the virtual authenticator.
+  /// Does nothing if the platform backend is used.
+  pub fn clear_credentials(&self) {
+    match self {
+      Webauthn::Virtual(webauthn) => webauthn.clear_credentials(),
+      Webauthn::Platform(_) => {
+        #[cfg(feature = "log")]
+        log::warn!("clear_credentials is only supported by the virtual authenticator");
+      }
+    }
+  }
+}
+
+impl<R: Runtime> Authenticator<R> for Webauthn<R> {
+  fn init(
+    app: &AppHandle<R>,
+    api: PluginApi<R, Option<Config>>,
+    config: &Config,
+  ) -> crate::Result<Self> {
+    Ok(match config.backend {
+      Backend::Platform => Webauthn::Platform(Platform::init(app, api, config)?),
+      Backend::Virtual => {
+        Webauthn::Virtual(virtual_authenticator::Webauthn::init(app, api, config)?)
+      }
+    })
+  }
+
+  async fn register(
+    &self,
+    origin: Url,
+    options: PublicKeyCredentialCreationOptions,
+    timeout: u32,
+    events: EventSink,
+  ) -> crate::Result<RegisterPublicKeyCredential> {
+    dispatch!(self.register(origin, options, timeout, events).await)
+  }
+
+  async fn authenticate(
+    &self,
+    origin: Url,
+    options: PublicKeyCredentialRequestOptions,
+    timeout: u32,
+    events: EventSink,
+  ) -> crate::Result<PublicKeyCredential> {
+    dispatch!(self.authenticate(origin, options, timeout, events).await)
+  }
+
+  async fn register_with_extensions(
+    &self,
+    origin: Url,
+    options: PublicKeyCredentialCreationOptions,
+    extensions: ExtensionInputs,
+    timeout: u32,
+    events: EventSink,
+  ) -> crate::Result<WithExtensions<RegisterPublicKeyCredential, ExtensionOutputs>> {
+    dispatch!(
+      self
+        .register_with_extensions(origin, options, extensions, timeout, events)
+        .await
+    )
+  }
+
+  async fn authenticate_with_extensions(
+    &self,
+    origin: Url,
+    options: PublicKeyCredentialRequestOptions,
+    extensions: ExtensionInputs,
+    timeout: u32,
+    events: EventSink,
+  ) -> crate::Result<WithExtensions<PublicKeyCredential, ExtensionOutputs>> {
+    dispatch!(
+      self
+        .authenticate_with_extensions(origin, options, extensions, timeout, events)
+        .await
+    )
+  }
+
+  fn send_pin(&self, request_id: u64, pin: String) -> crate::Result<()> {
+    dispatch!(self.send_pin(request_id, pin))
+  }
+
+  fn select_key(&self, request_id: u64, key: usize) -> crate::Result<()> {
+    dispatch!(self.select_key(request_id, key))
+  }
+
+  async fn pin_info(&self, timeout: u32, events: EventSink) -> crate::Result<PinInfo> {
+    dispatch!(self.pin_info(timeout, events).await)
+  }
+
+  async fn set_pin(&self, pin: String, timeout: u32, events: EventSink) -> crate::Result<()> {
+    dispatch!(self.set_pin(pin, timeout, events).await)
+  }
+
+  async fn change_pin(
+    &self,
+    current_pin: String,
+    new_pin: String,
+    timeout: u32,
+    events: EventSink,
+  ) -> crate::Result<()> {
+    dispatch!(self.change_pin(current_pin, new_pin, timeout, events).await)
+  }
+
+  async fn list_credentials(
+    &self,
+    timeout: u32,
+    events: EventSink,
+  ) -> crate::Result<CredentialList> {
+    dispatch!(self.list_credentials(timeout, events).await)
+  }
+
+  async fn delete_credential(
+    &self,
+    credential_id: Vec<u8>,
+    timeout: u32,
+    events: EventSink,
+  ) -> crate::Result<()> {
+    dispatch!(self.delete_credential(credential_id, timeout, events).await)
+  }
+
+  async fn update_credential_user(
+    &self,
+    credential_id: Vec<u8>,
+    user: CredentialUser,
+    timeout: u32,
+    events: EventSink,
+  ) -> crate::Result<()> {
+    dispatch!(
+      self
+        .update_credential_user(credential_id, user, timeout, events)
+        .await
+    )
+  }
+
+  async fn fingerprint_sensor_info(
+    &self,
+    timeout: u32,
+    events: EventSink,
+  ) -> crate::Result<FingerprintSensorInfo> {
+    dispatch!(self.fingerprint_sensor_info(timeout, events).await)
+  }
+
+  async fn list_enrollments(
+    &self,
+    timeout: u32,
+    events: EventSink,
+  ) -> crate::Result<Vec<Enrollment>> {
+    dispatch!(self.list_enrollments(timeout, events).await)
+  }
+
+  async fn enroll_fingerprint(
+    &self,
+    name: Option<String>,
+    timeout: u32,
+    events: EventSink,
+  ) -> crate::Result<()> {
+    dispatch!(self.enroll_fingerprint(name, timeout, events).await)
+  }
+
+  async fn rename_enrollment(
+    &self,
+    id: Vec<u8>,
+    name: String,
+    timeout: u32,
+    events: EventSink,
+  ) -> crate::Result<()> {
+    dispatch!(self.rename_enrollment(id, name, timeout, events).await)
+  }
+
+  async fn delete_enrollment(
+    &self,
+    id: Vec<u8>,
+    timeout: u32,
+    events: EventSink,
+  ) -> crate::Result<()> {
+    dispatch!(self.delete_enrollment(id, timeout, events).await)
+  }
+
+  async fn configure_authenticator(
+    &self,
+    change: ConfigChange,
+    timeout: u32,
+    events: EventSink,
+  ) -> crate::Result<AuthenticatorSettings> {
+    dispatch!(self.configure_authenticator(change, timeout, events).await)
+  }
+
+  fn prepare_reset(&self, owner: &str) -> crate::Result<String> {
+    dispatch!(self.prepare_reset(owner))
+  }
+
+  async fn reset(
+    &self,
+    confirmation: String,
+    owner: String,
+    timeout: u32,
+    events: EventSink,
+  ) -> crate::Result<()> {
+    dispatch!(self.reset(confirmation, owner, timeout, events).await)
+  }
+
+  fn cancel(&self) {
+    dispatch!(self.cancel())
+  }
+}
@@ -1,42 +1,122 @@
-use authenticator::{ctap2::server::PublicKeyCredentialUserEntity, StatusPinUv, StatusUpdate};
-use serde::{Deserialize, Serialize};
+use authenticator::{
+  ctap2::{
+    commands::bio_enrollment::LastEnrollmentSampleStatus, server::PublicKeyCredentialUserEntity,
+  },
+  Pin, StatusPinUv, StatusUpdate,
+};
+use serde::Serialize;
+use tauri::{AppHandle, Runtime};
+
+use super::hid::{DeviceChange, HidDevice};
+use crate::{
+  events::{
+    Account, DeviceInfo, EnrollmentSample, EventSink, Outcome, PinEvent, SampleStatus,
+    UserInteractionHandler,
+  },
+  OperationKind,
+};
 
-/// Nearly identical to the `StatusUpdate` enum, but serializable
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Progress of a ceremony, mostly built from the `StatusUpdate`s of the authenticator.
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase", tag = "type")]
 pub enum WebauthnEvent {
+  Started {
+    kind: OperationKind,
+  },
+  Finished {
+    outcome: Outcome,
+  },
   SelectDevice,
   PresenceRequired,
   PinEvent {
     event: PinEvent,
+    /// Id to pass to `send_pin`, only set if the authenticator waits for a PIN.
+    #[serde(rename = "requestId", skip_serializing_if = "Option::is_none")]
+    request_id: Option<u64>,
   },
   SelectKey {
     keys: Vec<PublicKeyCredentialUserEntity>,
+    /// Id to pass to `select_key`.
+    #[serde(rename = "requestId")]
+    request_id: u64,
+  },
+  /// The user has to unplug and re-insert the security key before it can be reset.
+  ReinsertKey,
+  /// A security key was plugged in, only reported on Linux.
+  DeviceAttached {
+    device: DeviceInfo,
+  },
+  /// A security key was unplugged, only reported on Linux.
+  DeviceRemoved {
+    device: DeviceInfo,
+  },
+  /// A fingerprint sample was captured during an enrollment.
+  EnrollmentSample {
+    #[serde(flatten)]
+    sample: EnrollmentSample,
   },
 }
 
-/// Nearly identical to the `StatusPinUv` enum, but serializable
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase", tag = "type")]
-pub enum PinEvent {
-  PinRequired,
-  InvalidPin { attempts_remaining: Option<u8> },
-  PinAuthBlocked,
-  PinBlocked,
-  InvalidUv { attempts_remaining: Option<u8> },
-  UvBlocked,
-}
-
-impl From<StatusUpdate> for WebauthnEvent {
-  fn from(status: StatusUpdate) -> Self {
-    match status {
+impl WebauthnEvent {
+  /// Converts a status update, `request_id` identifies the PIN or key selection request the update is waiting for.
+  /// Returns `None` for updates which are not part of a registration or authentication.
+  pub fn new(status: StatusUpdate, request_id: Option<u64>) -> Option<Self> {
+    Some(match status {
       StatusUpdate::SelectDeviceNotice => WebauthnEvent::SelectDevice,
       StatusUpdate::PresenceRequired => WebauthnEvent::PresenceRequired,
       StatusUpdate::PinUvError(event) => WebauthnEvent::PinEvent {
         event: event.into(),
+        request_id,
+      },
+      StatusUpdate::SelectResultNotice(.., users) => WebauthnEvent::SelectKey {
+        keys: users,
+        request_id: request_id.unwrap_or_default(),
+      },
+      StatusUpdate::InteractiveManagement(_) => return None,
+    })
+  }
+
+  /// Delivers the event to `events`, a handler only receives the lifecycle, device and enrollment events.
+  pub fn send<R: Runtime>(&self, app: &AppHandle<R>, events: &EventSink) {
+    match (events, self) {
+      (EventSink::Handler(handler), WebauthnEvent::Started { kind }) => handler.started(*kind),
+      (EventSink::Handler(handler), WebauthnEvent::Finished { outcome }) => {
+        handler.finished(*outcome)
+      }
+      (EventSink::Handler(handler), WebauthnEvent::EnrollmentSample { sample }) => {
+        handler.enrollment_sample(*sample)
+      }
+      (EventSink::Handler(handler), WebauthnEvent::ReinsertKey) => handler.reinsert_key(),
+      (EventSink::Handler(handler), WebauthnEvent::DeviceAttached { device }) => {
+        handler.device_attached(device.clone())
+      }
+      (EventSink::Handler(handler), WebauthnEvent::DeviceRemoved { device }) => {
+        handler.device_removed(device.clone())
+      }
+      (EventSink::Handler(_), _) => (),
+      (events, event) => events.send(app, event),
+    }
+  }
+}
+
+impl From<DeviceChange> for WebauthnEvent {
+  fn from(change: DeviceChange) -> Self {
+    match change {
+      DeviceChange::Attached(device) => WebauthnEvent::DeviceAttached {
+        device: device.into(),
+      },
+      DeviceChange::Removed(device) => WebauthnEvent::DeviceRemoved {
+        device: device.into(),
       },
-      StatusUpdate::SelectResultNotice(.., users) => WebauthnEvent::SelectKey { keys: users },
-      _ => unreachable!(),
+    }
+  }
+}
+
+impl From<HidDevice> for DeviceInfo {
+  fn from(device: HidDevice) -> Self {
+    DeviceInfo {
+      path: device.path.to_string_lossy().into_owned(),
+      name: device.name,
     }
   }
 }
@@ -54,7 +134,66 @@ impl From<StatusPinUv> for PinEvent {
         attempts_remaining: attempts,
       },
       StatusPinUv::UvBlocked => PinEvent::UvBlocked,
-      _ => unreachable!(),
+      StatusPinUv::PinIsTooShort => PinEvent::PinTooShort,
+      StatusPinUv::PinIsTooLong(length) => PinEvent::PinTooLong { length },
+      StatusPinUv::PinNotSet => PinEvent::PinNotSet,
+    }
+  }
+}
+
+/// Answers a status update with the callbacks of `handler` instead of emitting an event.
+pub fn handle(handler: &dyn UserInteractionHandler, status: StatusUpdate) {
+  match status {
+    StatusUpdate::SelectDeviceNotice => handler.select_device(),
+    StatusUpdate::PresenceRequired => handler.presence_required(),
+    StatusUpdate::PinUvError(StatusPinUv::PinRequired(sender)) => {
+      // dropping the sender cancels the ceremony
+      if let Some(pin) = handler.request_pin(None) {
+        let _ = sender.send(Pin::new(&pin));
+      }
+    }
+    StatusUpdate::PinUvError(StatusPinUv::InvalidPin(sender, attempts)) => {
+      if let Some(pin) = handler.request_pin(attempts) {
+        let _ = sender.send(Pin::new(&pin));
+      }
+    }
+    StatusUpdate::PinUvError(event) => handler.pin_event(event.into()),
+    StatusUpdate::SelectResultNotice(sender, users) => {
+      let accounts = users
+        .into_iter()
+        .map(|user| Account {
+          id: user.id,
+          name: user.name,
+          display_name: user.display_name,
+        })
+        .collect();
+      let _ = sender.send(handler.select_account(accounts));
+    }
+    StatusUpdate::InteractiveManagement(_) => (),
+  }
+}
+
+impl From<LastEnrollmentSampleStatus> for SampleStatus {
+  fn from(status: LastEnrollmentSampleStatus) -> Self {
+    match status {
+      LastEnrollmentSampleStatus::Ctap2EnrollFeedbackFpGood => SampleStatus::Good,
+      LastEnrollmentSampleStatus::Ctap2EnrollFeedbackFpTooHigh => SampleStatus::TooHigh,
+      LastEnrollmentSampleStatus::Ctap2EnrollFeedbackFpTooLow => SampleStatus::TooLow,
+      LastEnrollmentSampleStatus::Ctap2EnrollFeedbackFpTooLeft => SampleStatus::TooLeft,
+      LastEnrollmentSampleStatus::Ctap2EnrollFeedbackFpTooRight => SampleStatus::TooRight,
+      LastEnrollmentSampleStatus::Ctap2EnrollFeedbackFpTooFast => SampleStatus::TooFast,
+      LastEnrollmentSampleStatus::Ctap2EnrollFeedbackFpTooSlow => SampleStatus::TooSlow,
+      LastEnrollmentSampleStatus::Ctap2EnrollFeedbackFpPoorQuality => SampleStatus::PoorQuality,
+      LastEnrollmentSampleStatus::Ctap2EnrollFeedbackFpTooSkewed => SampleStatus::TooSkewed,
+      LastEnrollmentSampleStatus::Ctap2EnrollFeedbackFpTooShort => SampleStatus::TooShort,
+      LastEnrollmentSampleStatus::Ctap2EnrollFeedbackFpMergeFailure => SampleStatus::MergeFailure,
+      LastEnrollmentSampleStatus::Ctap2EnrollFeedbackFpExists => SampleStatus::Exists,
+      LastEnrollmentSampleStatus::Ctap2EnrollFeedbackNoUserActivity => SampleStatus::NoUserActivity,
+      LastEnrollmentSampleStatus::Ctap2EnrollFeedbackNoUserPresenceTransition => {
+        SampleStatus::NoUserPresenceTransition
+      }
+      LastEnrollmentSampleStatus::Unused
+      | LastEnrollmentSampleStatus::Ctap2EnrollFeedbackOther(_) => SampleStatus::Other,
     }
   }
 }
@@ -0,0 +1,349 @@
+use std::{
+  collections::HashMap,
+  fs::File,
+  io::{self, Read, Write},
+  os::fd::AsRawFd,
+  path::PathBuf,
+  sync::mpsc::{self, RecvTimeoutError},
+  thread,
+  time::{Duration, Instant},
+};
+
+/// Size of the HID reports of a FIDO device.
+const REPORT_SIZE: usize = 64;
+const BROADCAST_CHANNEL: [u8; 4] = [0xff; 4];
+const CTAPHID_INIT: u8 = 0x86;
+const CTAPHID_KEEPALIVE: u8 = 0xbb;
+const CTAPHID_ERROR: u8 = 0xbf;
+const CAPABILITY_CBOR: u8 = 0x04;
+const FIDO_USAGE_PAGE: u32 = 0xf1d0;
+/// Time to wait for a response, extended by every keepalive of the device.
+const TIMEOUT: Duration = Duration::from_secs(2);
+/// How often the connected devices are compared while they are watched.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A FIDO security key connected over USB.
+/// The authenticator service doesn't expose its devices, so they are enumerated separately.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HidDevice {
+  pub path: PathBuf,
+  /// The product name reported by the device.
+  pub name: Option<String>,
+}
+
+/// Lists the connected security keys, devices are only enumerated on Linux.
+#[cfg(target_os = "linux")]
+pub fn devices() -> Vec<HidDevice> {
+  let Ok(entries) = std::fs::read_dir("/sys/class/hidraw") else {
+    return Vec::new();
+  };
+  let mut devices: Vec<_> = entries
+    .flatten()
+    .filter_map(|entry| {
+      let device = entry.path().join("device");
+      let descriptor = std::fs::read(device.join("report_descriptor")).ok()?;
+      if !is_fido(&descriptor) {
+        return None;
+      }
+      let name = std::fs::read_to_string(device.join("uevent"))
+        .ok()
+        .and_then(|uevent| {
+          uevent
+            .lines()
+            .find_map(|line| line.strip_prefix("HID_NAME=").map(str::to_string))
+        });
+      Some(HidDevice {
+        path: PathBuf::from("/dev").join(entry.file_name()),
+        name,
+      })
+    })
+    .collect();
+  devices.sort_by(|a, b| a.path.cmp(&b.path));
+  devices
+}
+
+/// Lists the connected security keys, devices are only enumerated on Linux.
+#[cfg(not(target_os = "linux"))]
+pub fn devices() -> Vec<HidDevice> {
+  Vec::new()
+}
+
+/// A security key was plugged in or unplugged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceChange {
+  Attached(HidDevice),
+  Removed(HidDevice),
+}
+
+/// Watches the connected security keys, the watch ends when this is dropped.
+pub struct Watch {
+  _stop: mpsc::Sender<()>,
+}
+
+/// Calls `on_change` from a background thread for every security key plugged in or unplugged.
+/// The authenticator service doesn't report this, so the devices are polled.
+pub fn watch(mut on_change: impl FnMut(DeviceChange) + Send + 'static) -> Watch {
+  let (stop_tx, stop_rx) = mpsc::channel();
+  if cfg!(target_os = "linux") {
+    thread::spawn(move || {
+      let mut known = devices();
+      // the sender is never used, the watch ends once it is dropped
+      while let Err(RecvTimeoutError::Timeout) = stop_rx.recv_timeout(POLL_INTERVAL) {
+        let current = devices();
+        for device in &current {
+          if !known.contains(device) {
+            on_change(DeviceChange::Attached(device.clone()));
+          }
+        }
+        for device in known {
+          if !current.contains(&device) {
+            on_change(DeviceChange::Removed(device));
+          }
+        }
+        known = current;
+      }
+    });
+  }
+  Watch { _stop: stop_tx }
+}
+
+/// Whether one of the connected security keys only supports CTAP1/U2F.
+pub fn ctap1_only_connected() -> bool {
+  devices().iter().any(is_ctap1_only)
+}
+
+/// Whether the security key only supports CTAP1/U2F.
+pub fn is_ctap1_only(device: &HidDevice) -> bool {
+  Connection::open(device).is_ok_and(|connection| !connection.supports_cbor())
+}
+
+/// A CTAPHID channel to one security key.
+/// The channel is separate from the one of the authenticator service, so it can be used while a ceremony is running.
+pub struct Connection {
+  file: File,
+  channel: [u8; 4],
+  capabilities: u8,
+}
+
+impl Connection {
+  pub fn open(device: &HidDevice) -> crate::Result<Self> {
+    let file = File::options().read(true).write(true).open(&device.path)?;
+    let mut connection = Connection {
+      file,
+      channel: BROADCAST_CHANNEL,
+      capabilities: 0,
+    };
+    let mut nonce = [0; 8];
+    openssl::rand::rand_bytes(&mut nonce)?;
+    let response = connection.transact(CTAPHID_INIT, &nonce)?;
+    // nonce, channel id, CTAPHID version, device version and capabilities
+    if response.len() < 17 || response[..8] != nonce {
+      return Err(crate::Error::InvalidResponse);
+    }
+    connection.channel = response[8..12].try_into().unwrap();
+    connection.capabilities = response[16];
+    Ok(connection)
+  }
+
+  /// Keys without the CBOR capability only support CTAP1/U2F.
+  pub fn supports_cbor(&self) -> bool {
+    self.capabilities & CAPABILITY_CBOR != 0
+  }
+
+  fn transact(&mut self, command: u8, payload: &[u8]) -> crate::Result<Vec<u8>> {
+    for packet in packets(self.channel, command, payload) {
+      // hidraw expects the report id in front of the report
+      let mut report = [0; REPORT_SIZE + 1];
+      report[1..].copy_from_slice(&packet);
+      self.file.write_all(&report)?;
+    }
+
+    let mut messages = Messages::default();
+    let mut deadline = Instant::now() + TIMEOUT;
+    loop {
+      let Some(report) = read_report(&self.file, deadline)? else {
+        return Err(io::Error::from(io::ErrorKind::TimedOut).into());
+      };
+      let Some((channel, response, data)) = messages.push(&report) else {
+        continue;
+      };
+      match response {
+        _ if channel != self.channel => (),
+        CTAPHID_KEEPALIVE => deadline = Instant::now() + TIMEOUT,
+        CTAPHID_ERROR => return Err(crate::Error::InvalidResponse),
+        response if response == command => return Ok(data),
+        _ => return Err(crate::Error::InvalidResponse),
+      }
+    }
+  }
+}
+
+/// Waits for the next input report, `None` if the deadline has passed.
+fn read_report(file: &File, deadline: Instant) -> crate::Result<Option<[u8; REPORT_SIZE]>> {
+  let timeout = deadline
+    .saturating_duration_since(Instant::now())
+    .as_millis();
+  let mut poll = libc::pollfd {
+    fd: file.as_raw_fd(),
+    events: libc::POLLIN,
+    revents: 0,
+  };
+  // SAFETY: `poll` is a single valid pollfd which outlives the call
+  let ready = unsafe { libc::poll(&mut poll, 1, timeout.min(i32::MAX as u128) as i32) };
+  if ready < 0 {
+    return Err(io::Error::last_os_error().into());
+  }
+  if ready == 0 {
+    return Ok(None);
+  }
+  let mut report = [0; REPORT_SIZE];
+  if (&*file).read(&mut report)? == 0 {
+    return Err(crate::Error::NoToken);
+  }
+  Ok(Some(report))
+}
+
+/// Splits a message into an initialization packet and continuation packets.
+fn packets(channel: [u8; 4], command: u8, payload: &[u8]) -> Vec<[u8; REPORT_SIZE]> {
+  let (first, rest) = payload.split_at(payload.len().min(REPORT_SIZE - 7));
+  let mut packet = [0; REPORT_SIZE];
+  packet[..4].copy_from_slice(&channel);
+  packet[4] = command;
+  packet[5..7].copy_from_slice(&(payload.len() as u16).to_be_bytes());
+  packet[7..7 + first.len()].copy_from_slice(first);
+
+  let mut packets = vec![packet];
+  for (sequence, chunk) in rest.chunks(REPORT_SIZE - 5).enumerate() {
+    let mut packet = [0; REPORT_SIZE];
+    packet[..4].copy_from_slice(&channel);
+    packet[4] = sequence as u8;
+    packet[5..5 + chunk.len()].copy_from_slice(chunk);
+    packets.push(packet);
+  }
+  packets
+}
+
+struct PartialMessage {
+  command: u8,
+  length: usize,
+  sequence: u8,
+  data: Vec<u8>,
+}
+
+/// Reassembles the messages of all channels of a device from their packets.
+#[derive(Default)]
+struct Messages {
+  partial: HashMap<[u8; 4], PartialMessage>,
+}
+
+impl Messages {
+  /// Adds a packet, returns the channel, command and payload once a message is complete.
+  fn push(&mut self, packet: &[u8; REPORT_SIZE]) -> Option<([u8; 4], u8, Vec<u8>)> {
+    let channel: [u8; 4] = packet[..4].try_into().unwrap();
+    if packet[4] & 0x80 != 0 {
+      self.partial.insert(
+        channel,
+        PartialMessage {
+          command: packet[4],
+          length: u16::from_be_bytes([packet[5], packet[6]]) as usize,
+          sequence: 0,
+          data: packet[7..].to_vec(),
+        },
+      );
+    } else {
+      let message = self.partial.get_mut(&channel)?;
+      if packet[4] != message.sequence {
+        // a packet was lost, the message can't be completed
+        self.partial.remove(&channel);
+        return None;
+      }
+      message.sequence += 1;
+      message.data.extend(&packet[5..]);
+    }
+
+    if self.partial[&channel].data.len() < self.partial[&channel].length {
+      return None;
+    }
+    let mut message = self.partial.remove(&channel)?;
+    message.data.truncate(message.length);
+    Some((channel, message.command, message.data))
+  }
+}
+
+/// Whether a HID report descriptor declares the FIDO usage page.
+fn is_fido(descriptor: &[u8]) -> bool {
+  let mut items = descriptor;
+  while let Some((&prefix, rest)) = items.split_first() {
+    // long items have their size in the next byte
+    let size = match prefix {
+      0xfe => 2 + *rest.first().unwrap_or(&0) as usize,
+      _ if prefix & 0x03 == 0x03 => 4,
+      _ => (prefix & 0x03) as usize,
+    };
+    let Some(data) = rest.get(..size) else {
+      return false;
+    };
+    // the usage page is a global item with tag 0
+    if prefix & 0xfc == 0x04 {
+      let page = data
+        .iter()
+        .rev()
+        .fold(0, |page, &byte| page << 8 | byte as u32);
+      if page == FIDO_USAGE_PAGE {
+        return true;
+      }
+    }
+    items = &rest[size..];
+  }
+  false
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const CTAPHID_CBOR: u8 = 0x90;
+
+  #[test]
+  fn detects_fido_usage_page() {
+    // usage page (FIDO alliance), usage (CTAPHID), collection (application)
+    assert!(is_fido(&[0x06, 0xd0, 0xf1, 0x09, 0x01, 0xa1, 0x01, 0xc0]));
+    // usage page (generic desktop), usage (keyboard)
+    assert!(!is_fido(&[0x05, 0x01, 0x09, 0x06, 0xa1, 0x01, 0xc0]));
+    // truncated item
+    assert!(!is_fido(&[0x06, 0xd0]));
+  }
+
+  #[test]
+  fn reassembles_packets() {
+    let payload: Vec<u8> = (0..200).map(|i| i as u8).collect();
+    let packets = packets([1, 2, 3, 4], CTAPHID_CBOR, &payload);
+    assert_eq!(packets.len(), 4);
+
+    let mut messages = Messages::default();
+    // packets of other channels are interleaved
+    let other = super::packets([5, 6, 7, 8], CTAPHID_KEEPALIVE, &[1]);
+    assert_eq!(
+      messages.push(&other[0]),
+      Some(([5, 6, 7, 8], CTAPHID_KEEPALIVE, vec![1]))
+    );
+    for packet in &packets[..3] {
+      assert_eq!(messages.push(packet), None);
+    }
+    assert_eq!(
+      messages.push(&packets[3]),
+      Some(([1, 2, 3, 4], CTAPHID_CBOR, payload))
+    );
+  }
+
+  #[test]
+  fn drops_messages_with_lost_packets() {
+    let packets = packets([1, 2, 3, 4], CTAPHID_CBOR, &[0; 100]);
+    let mut messages = Messages::default();
+    assert_eq!(messages.push(&packets[0]), None);
+    let mut skipped = packets[1];
+    skipped[4] = 1;
+    assert_eq!(messages.push(&skipped), None);
+    assert_eq!(messages.push(&packets[1]), None);
+  }
+}
@@ -0,0 +1,317 @@
+use std::sync::{mpsc::Sender, Mutex};
+
+use authenticator::{
+  authenticatorservice::AuthenticatorService,
+  ctap2::commands::{
+    authenticator_config::{AuthConfigCommand, AuthConfigResult, SetMinPINLength},
+    bio_enrollment::{self, FingerprintKind as SensorKind},
+    credential_management, PinUvAuthResult,
+  },
+  statecallback::StateCallback,
+  AuthenticatorInfo, BioEnrollmentCmd, BioEnrollmentResult, CredManagementCmd,
+  CredentialManagementResult, InteractiveRequest, InteractiveUpdate, ManageResult, Pin,
+  StatusUpdate,
+};
+use tokio::sync::mpsc;
+use webauthn_rs_proto::CredentialProtectionPolicy;
+
+use crate::{
+  AuthenticatorSettings, ConfigChange, CredentialList, CredentialUser, Enrollment,
+  EnrollmentSample, Error, FingerprintKind, FingerprintSensorInfo, PinInfo,
+  RelyingPartyCredentials, Result, StoredCredential,
+};
+
+/// Maximum length of a PIN in bytes according to the CTAP2 spec.
+const MAX_PIN_LENGTH: usize = 63;
+
+/// Messages of a management session.
+/// Intermediate results are reported as status updates, the final result through the callback.
+pub enum Message {
+  Update(InteractiveUpdate),
+  Done(authenticator::Result<ManageResult>),
+}
+
+/// An interactive management session with a single security key.
+/// The session ends with [`Session::finish`] or when it is dropped.
+pub struct Session {
+  requests: Sender<InteractiveRequest>,
+  info: Option<AuthenticatorInfo>,
+  messages: mpsc::UnboundedReceiver<Message>,
+  puat: Option<PinUvAuthResult>,
+}
+
+impl Session {
+  /// Waits until the user selected a security key.
+  /// `status` creates the status thread, which has to forward interactive updates to the passed sender.
+  pub async fn open(
+    manager: &Mutex<AuthenticatorService>,
+    status: impl FnOnce(mpsc::UnboundedSender<Message>) -> Sender<StatusUpdate>,
+    timeout: u64,
+  ) -> Result<Self> {
+    let (messages_tx, mut messages) = mpsc::unbounded_channel();
+    let done_tx = messages_tx.clone();
+    let callback = StateCallback::new(Box::new(move |rv| {
+      let _ = done_tx.send(Message::Done(rv));
+    }));
+    manager
+      .lock()
+      .unwrap()
+      .manage(timeout, status(messages_tx), callback)?;
+
+    loop {
+      match messages.recv().await {
+        Some(Message::Update(InteractiveUpdate::StartManagement((requests, info)))) => {
+          return Ok(Session {
+            requests,
+            info,
+            messages,
+            puat: None,
+          })
+        }
+        Some(Message::Update(_)) => continue,
+        // the transaction ended before a key was selected, e.g. because of the timeout
+        Some(Message::Done(Err(e))) => return Err(e.into()),
+        Some(Message::Done(Ok(_))) | None => return Err(Error::Aborted),
+      }
+    }
+  }
+
+  pub fn info(&self) -> Result<&AuthenticatorInfo> {
+    self.info.as_ref().ok_or(Error::InvalidResponse)
+  }
+
+  pub fn send(&self, request: InteractiveRequest) -> Result<()> {
+    self.requests.send(request).map_err(|_| Error::Aborted)
+  }
+
+  /// Waits for the result of the last request, which ends the session.
+  pub async fn finish(mut self) -> Result<ManageResult> {
+    loop {
+      match self.messages.recv().await {
+        Some(Message::Update(_)) => continue,
+        Some(Message::Done(result)) => return Ok(result?),
+        None => return Err(Error::Aborted),
+      }
+    }
+  }
+
+  /// Waits for the update answering a request which keeps the session open.
+  async fn update(&mut self) -> Result<InteractiveUpdate> {
+    loop {
+      match self.messages.recv().await {
+        Some(Message::Update(InteractiveUpdate::StartManagement(_))) => continue,
+        Some(Message::Update(update)) => return Ok(update),
+        Some(Message::Done(Err(e))) => return Err(e.into()),
+        Some(Message::Done(Ok(_))) | None => return Err(Error::Aborted),
+      }
+    }
+  }
+
+  /// Runs a credential management command, requires the PIN or built-in user verification.
+  /// The PIN/UV auth token is reused for the next command, so the user is asked only once per session.
+  pub async fn credentials(
+    &mut self,
+    command: CredManagementCmd,
+  ) -> Result<CredentialManagementResult> {
+    let puat = self.puat.take();
+    self.send(InteractiveRequest::CredentialManagement(command, puat))?;
+    match self.update().await? {
+      InteractiveUpdate::CredentialManagementUpdate((result, puat)) => {
+        self.puat = puat;
+        Ok(result)
+      }
+      _ => Err(Error::InvalidResponse),
+    }
+  }
+
+  /// Runs a bio enrollment command, requires the PIN or built-in user verification.
+  /// During an enrollment `on_sample` is called for every captured sample.
+  pub async fn bio(
+    &mut self,
+    command: BioEnrollmentCmd,
+    on_sample: impl Fn(EnrollmentSample),
+  ) -> Result<BioEnrollmentResult> {
+    let puat = self.puat.take();
+    self.send(InteractiveRequest::BioEnrollment(command, puat))?;
+    loop {
+      match self.update().await? {
+        InteractiveUpdate::BioEnrollmentUpdate((
+          BioEnrollmentResult::SampleStatus(status, remaining_samples),
+          _,
+        )) => on_sample(EnrollmentSample {
+          status: status.into(),
+          remaining_samples,
+        }),
+        InteractiveUpdate::BioEnrollmentUpdate((result, puat)) => {
+          self.puat = puat;
+          return Ok(result);
+        }
+        _ => return Err(Error::InvalidResponse),
+      }
+    }
+  }
+
+  /// Changes the configuration, requires the PIN or built-in user verification.
+  pub async fn configure(&mut self, change: ConfigChange) -> Result<AuthenticatorSettings> {
+    let command = match change {
+      ConfigChange::EnableEnterpriseAttestation => AuthConfigCommand::EnableEnterpriseAttestation,
+      ConfigChange::ToggleAlwaysUv => AuthConfigCommand::ToggleAlwaysUv,
+      ConfigChange::SetMinPinLength {
+        min_pin_length,
+        rp_ids,
+        force_change_pin,
+      } => AuthConfigCommand::SetMinPINLength(SetMinPINLength {
+        new_min_pin_length: min_pin_length,
+        min_pin_length_rpids: (!rp_ids.is_empty()).then_some(rp_ids),
+        force_change_pin: force_change_pin.then_some(true),
+      }),
+    };
+    let puat = self.puat.take();
+    self.send(InteractiveRequest::ChangeConfig(command, puat))?;
+    match self.update().await? {
+      InteractiveUpdate::AuthConfigUpdate((AuthConfigResult::Success(info), puat)) => {
+        self.puat = puat;
+        Ok(AuthenticatorSettings {
+          always_uv: info.options.always_uv == Some(true),
+          enterprise_attestation: info.options.ep == Some(true),
+          min_pin_length: min_pin_length(&info),
+          force_pin_change: info.force_pin_change == Some(true),
+        })
+      }
+      _ => Err(Error::InvalidResponse),
+    }
+  }
+
+  /// Ends the session without changing the security key.
+  pub async fn quit(self) -> Result<()> {
+    self.send(InteractiveRequest::Quit)?;
+    self.finish().await.map(|_| ())
+  }
+
+  pub fn pin_info(&self) -> Result<PinInfo> {
+    let info = self.info()?;
+    Ok(PinInfo {
+      supported: info.options.client_pin.is_some(),
+      is_set: info.options.client_pin == Some(true),
+      min_length: min_pin_length(info),
+      force_change: info.force_pin_change == Some(true),
+      uv_supported: info.options.user_verification.is_some(),
+      uv_configured: info.options.user_verification == Some(true),
+      // the interactive management of the authenticator service has no getPINRetries/getUVRetries
+      pin_retries: None,
+      uv_retries: None,
+    })
+  }
+
+  /// Sets the first PIN of the security key.
+  pub async fn set_pin(self, pin: String) -> Result<()> {
+    if let Err(e) = self.check_pin(&pin, false) {
+      self.quit().await?;
+      return Err(e);
+    }
+    self.send(InteractiveRequest::SetPIN(Pin::new(&pin)))?;
+    self.finish().await.map(|_| ())
+  }
+
+  /// Replaces the PIN, the user is asked again if `current_pin` is wrong.
+  pub async fn change_pin(self, current_pin: String, new_pin: String) -> Result<()> {
+    if let Err(e) = self.check_pin(&new_pin, true) {
+      self.quit().await?;
+      return Err(e);
+    }
+    self.send(InteractiveRequest::ChangePIN(
+      Pin::new(&current_pin),
+      Pin::new(&new_pin),
+    ))?;
+    self.finish().await.map(|_| ())
+  }
+
+  /// Checks the state of the security key and the PIN policy before the PIN is sent.
+  fn check_pin(&self, pin: &str, change: bool) -> Result<()> {
+    let info = self.info()?;
+    match (info.options.client_pin, change) {
+      (None, _) => return Err(Error::Unsupported("PIN")),
+      (Some(true), false) => return Err(Error::PinAlreadySet),
+      (Some(false), true) => return Err(Error::PinNotSet),
+      _ => (),
+    }
+    // the minimum length is counted in code points, the maximum in bytes
+    let min_length = min_pin_length(info);
+    if (pin.chars().count() as u64) < min_length {
+      return Err(Error::PinTooShort { min_length });
+    }
+    if pin.len() > MAX_PIN_LENGTH {
+      return Err(Error::PinTooLong {
+        max_length: MAX_PIN_LENGTH,
+      });
+    }
+    Ok(())
+  }
+}
+
+/// Keys which don't report a minimum PIN length use the default of 4 from the spec.
+fn min_pin_length(info: &AuthenticatorInfo) -> u64 {
+  info.min_pin_length.unwrap_or(4)
+}
+
+impl From<credential_management::CredentialList> for CredentialList {
+  fn from(list: credential_management::CredentialList) -> Self {
+    CredentialList {
+      existing: list.existing_resident_credentials_count,
+      remaining: list.max_possible_remaining_resident_credentials_count,
+      relying_parties: list
+        .credential_list
+        .into_iter()
+        .map(|entry| RelyingPartyCredentials {
+          rp_id: entry.rp.id,
+          rp_name: entry.rp.name,
+          credentials: entry
+            .credentials
+            .into_iter()
+            .map(|credential| StoredCredential {
+              id: credential.credential_id.id.into(),
+              user: CredentialUser {
+                id: credential.user.id.into(),
+                name: credential.user.name,
+                display_name: credential.user.display_name,
+              },
+              cred_protect: cred_protect_policy(credential.cred_protect),
+            })
+            .collect(),
+        })
+        .collect(),
+    }
+  }
+}
+
+fn cred_protect_policy(value: u64) -> Option<CredentialProtectionPolicy> {
+  match value {
+    1 => Some(CredentialProtectionPolicy::UserVerificationOptional),
+    2 => Some(CredentialProtectionPolicy::UserVerificationOptionalWithCredentialIDList),
+    3 => Some(CredentialProtectionPolicy::UserVerificationRequired),
+    _ => None,
+  }
+}
+
+impl From<bio_enrollment::FingerprintSensorInfo> for FingerprintSensorInfo {
+  fn from(info: bio_enrollment::FingerprintSensorInfo) -> Self {
+    FingerprintSensorInfo {
+      kind: match info.fingerprint_kind {
+        SensorKind::TouchSensor => FingerprintKind::Touch,
+        SensorKind::SwipeSensor => FingerprintKind::Swipe,
+        SensorKind::Other(_) => FingerprintKind::Other,
+      },
+      max_samples: info.max_capture_samples_required_for_enroll,
+      max_name_length: info.max_template_friendly_name,
+    }
+  }
+}
+
+impl From<bio_enrollment::EnrollmentInfo> for Enrollment {
+  fn from(info: bio_enrollment::EnrollmentInfo) -> Self {
+    Enrollment {
+      id: info.template_id.into(),
+      name: info.template_friendly_name,
+    }
+  }
+}
@@ -1,115 +1,574 @@
 use std::{
-  marker::PhantomData,
-  sync::{mpsc::Sender, Mutex},
+  future::Future,
+  pin::pin,
+  sync::{mpsc::Sender, Arc, Mutex},
+  time::Duration,
 };
 
-use authenticator::{authenticatorservice::AuthenticatorService, Pin, StatusUpdate};
+use authenticator::{
+  authenticatorservice::AuthenticatorService,
+  ctap2::server::{PublicKeyCredentialDescriptor, PublicKeyCredentialUserEntity},
+  BioEnrollmentCmd, BioEnrollmentResult, CredManagementCmd, CredentialManagementResult, Pin,
+  StatusUpdate,
+};
+use event::WebauthnEvent;
+use futures::future::{select, Either};
+use hid::DeviceChange;
+use management::{Message, Session};
+use pending::PendingRequests;
 use platform::AuthenticatorExt;
-use serde::de::DeserializeOwned;
-use tauri::{plugin::PluginApi, AppHandle, Runtime, Url};
-use tokio::sync::mpsc;
+use reset::ResetConfirmation;
+use tauri::{async_runtime::spawn_blocking, plugin::PluginApi, AppHandle, Runtime, Url};
+use tokio::sync::{mpsc, oneshot};
 use webauthn_rs_proto::{
   PublicKeyCredential, PublicKeyCredentialCreationOptions, PublicKeyCredentialRequestOptions,
   RegisterPublicKeyCredential,
 };
 
 use super::Authenticator;
+use crate::{
+  events::{EventSink, Outcome},
+  extensions::{ExtensionInputs, ExtensionOutputs, WithExtensions},
+  AuthenticatorSettings, Config, ConfigChange, CredentialList, CredentialUser, Enrollment,
+  FingerprintSensorInfo, OperationKind, PinInfo,
+};
 
 mod event;
+mod hid;
+mod management;
+mod pending;
 mod platform;
-
-pub const EVENT_NAME: &str = "tauri-plugin-webauthn";
+mod reset;
 
 pub struct Webauthn<R: Runtime> {
   manager: Mutex<AuthenticatorService>,
-  status_tx: Sender<StatusUpdate>,
-  pin_receiver: Mutex<mpsc::Receiver<Sender<Pin>>>,
-  select_receiver: Mutex<mpsc::Receiver<Sender<Option<usize>>>>,
-  phantom: PhantomData<AppHandle<R>>,
+  ctap1_fallback: bool,
+  pending: Arc<PendingRequests>,
+  reset: ResetConfirmation,
+  /// Cancels the wait for the re-inserted key of a reset.
+  reinsertion: Mutex<Option<oneshot::Sender<()>>>,
+  app: AppHandle<R>,
+}
+
+impl<R: Runtime> Webauthn<R> {
+  /// Wraps one interaction with the authenticator in the started and finished events,
+  /// security keys plugged in or unplugged in between are reported as well.
+  async fn run<T>(
+    &self,
+    kind: OperationKind,
+    events: &EventSink,
+    interaction: impl Future<Output = crate::Result<T>>,
+  ) -> crate::Result<T> {
+    WebauthnEvent::Started { kind }.send(&self.app, events);
+    let watch = {
+      let (app, events) = (self.app.clone(), events.clone());
+      hid::watch(move |change| WebauthnEvent::from(change).send(&app, &events))
+    };
+    let result = interaction.await;
+    drop(watch);
+    self.pending.clear();
+    WebauthnEvent::Finished {
+      outcome: Outcome::of(&result),
+    }
+    .send(&self.app, events);
+    result
+  }
+
+  fn status(
+    &self,
+    events: &EventSink,
+    management: Option<mpsc::UnboundedSender<Message>>,
+  ) -> Sender<StatusUpdate> {
+    platform::status(
+      self.app.clone(),
+      self.pending.clone(),
+      events.clone(),
+      management,
+    )
+  }
+
+  /// Refuses the ceremony if CTAP1 is disabled and a CTAP1/U2F-only key is connected,
+  /// the authenticator service would otherwise fall back to CTAP1 for it.
+  /// Such keys plugged in while the ceremony is running cancel it.
+  async fn without_ctap1<T>(
+    &self,
+    ceremony: impl Future<Output = crate::Result<T>>,
+  ) -> crate::Result<T> {
+    if self.ctap1_fallback {
+      return ceremony.await;
+    }
+    match spawn_blocking(hid::ctap1_only_connected).await {
+      Ok(false) => (),
+      Ok(true) => return Err(crate::Error::Ctap1NotAllowed),
+      Err(_) => return Err(crate::Error::Aborted),
+    }
+
+    let (refused_tx, refused_rx) = oneshot::channel();
+    let mut refused_tx = Some(refused_tx);
+    let _watch = hid::watch(move |change| {
+      if let DeviceChange::Attached(device) = change {
+        if hid::is_ctap1_only(&device) {
+          if let Some(refused_tx) = refused_tx.take() {
+            let _ = refused_tx.send(());
+          }
+        }
+      }
+    });
+    match select(pin!(ceremony), refused_rx).await {
+      Either::Left((result, _)) => result,
+      Either::Right((Ok(()), ceremony)) => {
+        self.cancel();
+        let _ = ceremony.await;
+        Err(crate::Error::Ctap1NotAllowed)
+      }
+      Either::Right((Err(_), ceremony)) => ceremony.await,
+    }
+  }
+
+  /// Waits until a security key is unplugged and plugged in again, so a reset is sent within its insertion window.
+  /// Devices are only watched on Linux, elsewhere the key has to be plugged in right before the reset.
+  async fn reinserted(&self, timeout: u32) -> crate::Result<()> {
+    if !cfg!(target_os = "linux") {
+      return Ok(());
+    }
+    let (reinserted_tx, reinserted_rx) = oneshot::channel();
+    let (mut reinserted_tx, mut removed) = (Some(reinserted_tx), false);
+    let _watch = hid::watch(move |change| match change {
+      DeviceChange::Removed(_) => removed = true,
+      DeviceChange::Attached(_) if removed => {
+        if let Some(reinserted_tx) = reinserted_tx.take() {
+          let _ = reinserted_tx.send(());
+        }
+      }
+      DeviceChange::Attached(_) => (),
+    });
+
+    let (cancel_tx, cancel_rx) = oneshot::channel::<()>();
+    *self.reinsertion.lock().unwrap() = Some(cancel_tx);
+    let waited = tokio::time::timeout(
+      Duration::from_millis(timeout.into()),
+      select(reinserted_rx, cancel_rx),
+    )
+    .await;
+    self.reinsertion.lock().unwrap().take();
+    match waited {
+      Ok(Either::Left((Ok(()), _))) => Ok(()),
+      Ok(_) => Err(crate::Error::Aborted),
+      Err(_) => Err(crate::Error::NotAllowed),
+    }
+  }
+
+  /// Opens a management session with the security key selected by the user.
+  async fn session(&self, timeout: u32, events: &EventSink) -> crate::Result<Session> {
+    Session::open(
+      &self.manager,
+      |messages| self.status(events, Some(messages)),
+      timeout as u64,
+    )
+    .await
+  }
 }
 
 impl<R: Runtime> Authenticator<R> for Webauthn<R> {
-  fn init<C: DeserializeOwned>(app: &AppHandle<R>, _api: PluginApi<R, C>) -> crate::Result<Self> {
-    let (pin_sender, pin_receiver) = mpsc::channel(100000);
-    let (select_sender, select_receiver) = mpsc::channel(100000);
+  fn init(
+    app: &AppHandle<R>,
+    _api: PluginApi<R, Option<Config>>,
+    config: &Config,
+  ) -> crate::Result<Self> {
     Ok(Webauthn {
-      manager: Mutex::new(platform::init_manager()?),
-      status_tx: platform::status(app.clone(), pin_sender, select_sender),
-      pin_receiver: Mutex::new(pin_receiver),
-      select_receiver: Mutex::new(select_receiver),
-      phantom: PhantomData,
+      manager: Mutex::new(platform::init_manager(config)?),
+      ctap1_fallback: config.ctap1_fallback,
+      pending: Arc::new(PendingRequests::new()),
+      reset: ResetConfirmation::new(),
+      reinsertion: Mutex::new(None),
+      app: app.clone(),
     })
   }
 
   /// Register a new credential using ctap2.
-  fn register(
+  async fn register(
     &self,
     origin: Url,
     options: PublicKeyCredentialCreationOptions,
     timeout: u32,
+    events: EventSink,
   ) -> crate::Result<RegisterPublicKeyCredential> {
-    #[cfg(feature = "log")]
-    log::info!("Registering with options: {options:?}");
-    let mut manager = self.manager.lock().unwrap();
-    manager
-      .perform_register(self.status_tx.clone(), origin, options, timeout as u64)
-      .map_err(|e| {
-        #[cfg(feature = "log")]
-        log::error!("Failed to register: {e:?}");
-        e
-      })
+    self
+      .register_with_extensions(origin, options, Default::default(), timeout, events)
+      .await
+      .map(|credential| credential.inner)
   }
 
   /// Authenticate using ctap2.
-  fn authenticate(
+  async fn authenticate(
     &self,
     origin: Url,
     options: PublicKeyCredentialRequestOptions,
     timeout: u32,
+    events: EventSink,
   ) -> crate::Result<PublicKeyCredential> {
+    self
+      .authenticate_with_extensions(origin, options, Default::default(), timeout, events)
+      .await
+      .map(|credential| credential.inner)
+  }
+
+  /// Register a new credential using ctap2, `prf` is evaluated with `hmac-secret`.
+  async fn register_with_extensions(
+    &self,
+    origin: Url,
+    options: PublicKeyCredentialCreationOptions,
+    extensions: ExtensionInputs,
+    timeout: u32,
+    events: EventSink,
+  ) -> crate::Result<WithExtensions<RegisterPublicKeyCredential, ExtensionOutputs>> {
+    #[cfg(feature = "log")]
+    log::info!("Registering with options: {options:?}");
+    super::check_extensions(&extensions)?;
+    let result = self
+      .run(
+        OperationKind::Register,
+        &events,
+        self.without_ctap1(self.manager.perform_register(
+          self.status(&events, None),
+          origin,
+          options,
+          extensions,
+          timeout as u64,
+          self.ctap1_fallback,
+        )),
+      )
+      .await;
+    result.map_err(|e| {
+      #[cfg(feature = "log")]
+      log::error!("Failed to register: {e:?}");
+      e
+    })
+  }
+
+  /// Authenticate using ctap2, `prf` is evaluated with `hmac-secret`.
+  async fn authenticate_with_extensions(
+    &self,
+    origin: Url,
+    options: PublicKeyCredentialRequestOptions,
+    extensions: ExtensionInputs,
+    timeout: u32,
+    events: EventSink,
+  ) -> crate::Result<WithExtensions<PublicKeyCredential, ExtensionOutputs>> {
     #[cfg(feature = "log")]
     log::debug!("Authenticating with options: {options:?}");
-    let mut manager = self.manager.lock().unwrap();
-    manager
-      .perform_authentication(self.status_tx.clone(), origin, options, timeout as u64)
-      .map_err(|e| {
-        #[cfg(feature = "log")]
-        log::error!("Failed to authenticate: {e:?}");
-        e
+    super::check_extensions(&extensions)?;
+    let result = self
+      .run(
+        OperationKind::Authenticate,
+        &events,
+        self.without_ctap1(self.manager.perform_authentication(
+          self.status(&events, None),
+          origin,
+          options,
+          extensions,
+          timeout as u64,
+        )),
+      )
+      .await;
+    result.map_err(|e| {
+      #[cfg(feature = "log")]
+      log::error!("Failed to authenticate: {e:?}");
+      e
+    })
+  }
+
+  /// Send a PIN for the request `request_id` received via the Event.
+  fn send_pin(&self, request_id: u64, pin: String) -> crate::Result<()> {
+    #[cfg(feature = "log")]
+    log::debug!("Sending pin for request {request_id}");
+    self.pending.send_pin(request_id, Pin::new(&pin))
+  }
+
+  /// Select a key for the request `request_id` where key is the index into the list received via the Event.
+  fn select_key(&self, request_id: u64, key: usize) -> crate::Result<()> {
+    #[cfg(feature = "log")]
+    log::debug!("Selecting key {key} for request {request_id}");
+    self.pending.select(request_id, key)
+  }
+
+  /// Query the PIN state using ctap2.
+  async fn pin_info(&self, timeout: u32, events: EventSink) -> crate::Result<PinInfo> {
+    self
+      .run(OperationKind::Manage, &events, async {
+        let session = self.session(timeout, &events).await?;
+        let info = session.pin_info();
+        session.quit().await?;
+        info
       })
+      .await
   }
 
-  /// Send a PIN to the authenticator does nothing if no PIN was requested.
-  fn send_pin(&self, pin: String) {
+  /// Set the PIN using ctap2.
+  async fn set_pin(&self, pin: String, timeout: u32, events: EventSink) -> crate::Result<()> {
     #[cfg(feature = "log")]
-    log::debug!("Sending pin");
-    let mut last_sender = None;
-    while let Ok(sender) = self.pin_receiver.lock().unwrap().try_recv() {
-      last_sender = Some(sender);
-    }
-    if let Some(sender) = last_sender {
-      let _ = sender.send(Pin::new(&pin));
-    }
+    log::debug!("Setting PIN");
+    self
+      .run(OperationKind::Manage, &events, async {
+        self.session(timeout, &events).await?.set_pin(pin).await
+      })
+      .await
   }
 
-  /// Select a key from the authenticator where key is the index into the list received via the Event.
-  /// Does nothing if no selection was requested.
-  fn select_key(&self, key: usize) {
+  /// Change the PIN using ctap2.
+  async fn change_pin(
+    &self,
+    current_pin: String,
+    new_pin: String,
+    timeout: u32,
+    events: EventSink,
+  ) -> crate::Result<()> {
     #[cfg(feature = "log")]
-    log::debug!("Selecting key {key}");
-    let mut last_sender = None;
-    while let Ok(sender) = self.select_receiver.lock().unwrap().try_recv() {
-      last_sender = Some(sender);
-    }
-    if let Some(sender) = last_sender {
-      let _ = sender.send(Some(key));
-    }
+    log::debug!("Changing PIN");
+    self
+      .run(OperationKind::Manage, &events, async {
+        self
+          .session(timeout, &events)
+          .await?
+          .change_pin(current_pin, new_pin)
+          .await
+      })
+      .await
+  }
+
+  /// List the discoverable credentials using ctap2.
+  async fn list_credentials(
+    &self,
+    timeout: u32,
+    events: EventSink,
+  ) -> crate::Result<CredentialList> {
+    self
+      .run(OperationKind::Manage, &events, async {
+        let mut session = self.session(timeout, &events).await?;
+        let result = session
+          .credentials(CredManagementCmd::GetCredentials)
+          .await?;
+        session.quit().await?;
+        match result {
+          CredentialManagementResult::CredentialList(list) => Ok(list.into()),
+          _ => Err(crate::Error::InvalidResponse),
+        }
+      })
+      .await
+  }
+
+  /// Delete a discoverable credential using ctap2.
+  async fn delete_credential(
+    &self,
+    credential_id: Vec<u8>,
+    timeout: u32,
+    events: EventSink,
+  ) -> crate::Result<()> {
+    #[cfg(feature = "log")]
+    log::debug!("Deleting credential");
+    let credential = PublicKeyCredentialDescriptor {
+      id: credential_id,
+      transports: Vec::new(),
+    };
+    self
+      .run(OperationKind::Manage, &events, async {
+        let mut session = self.session(timeout, &events).await?;
+        session
+          .credentials(CredManagementCmd::DeleteCredential(credential))
+          .await?;
+        session.quit().await
+      })
+      .await
+  }
+
+  /// Update the user of a discoverable credential using ctap2.
+  async fn update_credential_user(
+    &self,
+    credential_id: Vec<u8>,
+    user: CredentialUser,
+    timeout: u32,
+    events: EventSink,
+  ) -> crate::Result<()> {
+    #[cfg(feature = "log")]
+    log::debug!("Updating the user of a credential");
+    let credential = PublicKeyCredentialDescriptor {
+      id: credential_id,
+      transports: Vec::new(),
+    };
+    let user = PublicKeyCredentialUserEntity {
+      id: user.id.into(),
+      name: user.name,
+      display_name: user.display_name,
+    };
+    self
+      .run(OperationKind::Manage, &events, async {
+        let mut session = self.session(timeout, &events).await?;
+        session
+          .credentials(CredManagementCmd::UpdateUserInformation(credential, user))
+          .await?;
+        session.quit().await
+      })
+      .await
+  }
+
+  /// Query the fingerprint sensor using ctap2.
+  async fn fingerprint_sensor_info(
+    &self,
+    timeout: u32,
+    events: EventSink,
+  ) -> crate::Result<FingerprintSensorInfo> {
+    self
+      .run(OperationKind::Manage, &events, async {
+        let mut session = self.session(timeout, &events).await?;
+        let result = session
+          .bio(BioEnrollmentCmd::GetFingerprintSensorInfo, |_| ())
+          .await?;
+        session.quit().await?;
+        match result {
+          BioEnrollmentResult::FingerprintSensorInfo(info) => Ok(info.into()),
+          _ => Err(crate::Error::InvalidResponse),
+        }
+      })
+      .await
+  }
+
+  /// List the enrolled fingerprints using ctap2.
+  async fn list_enrollments(
+    &self,
+    timeout: u32,
+    events: EventSink,
+  ) -> crate::Result<Vec<Enrollment>> {
+    self
+      .run(OperationKind::Manage, &events, async {
+        let mut session = self.session(timeout, &events).await?;
+        let result = session
+          .bio(BioEnrollmentCmd::GetEnrollments, |_| ())
+          .await?;
+        session.quit().await?;
+        match result {
+          BioEnrollmentResult::EnrollmentList(list) => {
+            Ok(list.into_iter().map(Into::into).collect())
+          }
+          _ => Err(crate::Error::InvalidResponse),
+        }
+      })
+      .await
+  }
+
+  /// Enroll a fingerprint using ctap2.
+  async fn enroll_fingerprint(
+    &self,
+    name: Option<String>,
+    timeout: u32,
+    events: EventSink,
+  ) -> crate::Result<()> {
+    #[cfg(feature = "log")]
+    log::debug!("Enrolling fingerprint");
+    self
+      .run(OperationKind::Manage, &events, async {
+        let mut session = self.session(timeout, &events).await?;
+        session
+          .bio(BioEnrollmentCmd::StartNewEnrollment(name), |sample| {
+            WebauthnEvent::EnrollmentSample { sample }.send(&self.app, &events)
+          })
+          .await?;
+        session.quit().await
+      })
+      .await
+  }
+
+  /// Rename an enrolled fingerprint using ctap2.
+  async fn rename_enrollment(
+    &self,
+    id: Vec<u8>,
+    name: String,
+    timeout: u32,
+    events: EventSink,
+  ) -> crate::Result<()> {
+    self
+      .run(OperationKind::Manage, &events, async {
+        let mut session = self.session(timeout, &events).await?;
+        session
+          .bio(BioEnrollmentCmd::ChangeName(id, name), |_| ())
+          .await?;
+        session.quit().await
+      })
+      .await
+  }
+
+  /// Remove an enrolled fingerprint using ctap2.
+  async fn delete_enrollment(
+    &self,
+    id: Vec<u8>,
+    timeout: u32,
+    events: EventSink,
+  ) -> crate::Result<()> {
+    #[cfg(feature = "log")]
+    log::debug!("Deleting fingerprint enrollment");
+    self
+      .run(OperationKind::Manage, &events, async {
+        let mut session = self.session(timeout, &events).await?;
+        session
+          .bio(BioEnrollmentCmd::DeleteEnrollment(id), |_| ())
+          .await?;
+        session.quit().await
+      })
+      .await
+  }
+
+  /// Change the configuration using ctap2.
+  async fn configure_authenticator(
+    &self,
+    change: ConfigChange,
+    timeout: u32,
+    events: EventSink,
+  ) -> crate::Result<AuthenticatorSettings> {
+    #[cfg(feature = "log")]
+    log::info!("Changing authenticator config: {change:?}");
+    self
+      .run(OperationKind::Manage, &events, async {
+        let mut session = self.session(timeout, &events).await?;
+        let settings = session.configure(change).await?;
+        session.quit().await?;
+        Ok(settings)
+      })
+      .await
+  }
+
+  /// Issue a new reset confirmation token for `owner`.
+  fn prepare_reset(&self, owner: &str) -> crate::Result<String> {
+    self.reset.issue(owner)
+  }
+
+  /// Reset the security key using ctap2.
+  /// Keys only accept a reset shortly after they were plugged in, so the reset is sent once the user re-inserted the key.
+  async fn reset(
+    &self,
+    confirmation: String,
+    owner: String,
+    timeout: u32,
+    events: EventSink,
+  ) -> crate::Result<()> {
+    self.reset.confirm(&confirmation, &owner)?;
+    #[cfg(feature = "log")]
+    log::warn!("Resetting security key");
+    self
+      .run(OperationKind::Manage, &events, async {
+        WebauthnEvent::ReinsertKey.send(&self.app, &events);
+        self.reinserted(timeout).await?;
+        self
+          .manager
+          .perform_reset(self.status(&events, None), timeout as u64)
+          .await
+      })
+      .await
   }
 
   /// Cancel the current operation.
   fn cancel(&self) {
     #[cfg(feature = "log")]
     log::debug!("Cancelling operation");
+    self.reinsertion.lock().unwrap().take();
     let _ = self.manager.lock().unwrap().cancel();
   }
 }
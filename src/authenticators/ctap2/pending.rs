@@ -0,0 +1,72 @@
+use std::sync::{
+  atomic::{AtomicU64, Ordering},
+  mpsc::Sender,
+  Mutex,
+};
+
+use authenticator::Pin;
+
+use crate::Error;
+
+/// PIN and key selection requests of the authenticator which are waiting for a reply from the user.
+/// Every request gets a new id, replies are only accepted for the latest request of each kind.
+pub struct PendingRequests {
+  next_id: AtomicU64,
+  pin: Mutex<Option<(u64, Sender<Pin>)>>,
+  selection: Mutex<Option<(u64, Sender<Option<usize>>)>>,
+}
+
+impl PendingRequests {
+  pub fn new() -> Self {
+    PendingRequests {
+      next_id: AtomicU64::new(1),
+      pin: Mutex::new(None),
+      selection: Mutex::new(None),
+    }
+  }
+
+  /// Stores a PIN request and returns its id, replacing any previous PIN request.
+  pub fn request_pin(&self, sender: Sender<Pin>) -> u64 {
+    let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+    *self.pin.lock().unwrap() = Some((id, sender));
+    id
+  }
+
+  /// Stores a key selection request and returns its id, replacing any previous selection request.
+  pub fn request_selection(&self, sender: Sender<Option<usize>>) -> u64 {
+    let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+    *self.selection.lock().unwrap() = Some((id, sender));
+    id
+  }
+
+  /// Answers the PIN request `id`.
+  pub fn send_pin(&self, id: u64, pin: Pin) -> crate::Result<()> {
+    let sender = take(&self.pin, id)?;
+    sender.send(pin).map_err(|_| Error::RequestNotPending(id))
+  }
+
+  /// Answers the key selection request `id`.
+  pub fn select(&self, id: u64, key: usize) -> crate::Result<()> {
+    let sender = take(&self.selection, id)?;
+    sender
+      .send(Some(key))
+      .map_err(|_| Error::RequestNotPending(id))
+  }
+
+  /// Drops all requests, e.g. because the ceremony has finished.
+  pub fn clear(&self) {
+    self.pin.lock().unwrap().take();
+    self.selection.lock().unwrap().take();
+  }
+}
+
+fn take<T>(request: &Mutex<Option<(u64, Sender<T>)>>, id: u64) -> crate::Result<Sender<T>> {
+  let mut request = request.lock().unwrap();
+  match request.take() {
+    Some((pending, sender)) if pending == id => Ok(sender),
+    other => {
+      *request = other;
+      Err(Error::RequestNotPending(id))
+    }
+  }
+}
@@ -1,68 +1,97 @@
 use std::{
-  sync::mpsc::{channel, Sender},
+  sync::{
+    mpsc::{channel, Sender},
+    Arc, Mutex,
+  },
   thread,
 };
 
 use authenticator::{
   authenticatorservice::{AuthenticatorService, RegisterArgs, SignArgs},
   crypto::COSEAlgorithm,
+  ctap2::attestation::{self, AttestationStatement, Extension},
+  ctap2::commands::{CommandError, StatusCode},
   ctap2::server::{
     AuthenticationExtensionsClientInputs, AuthenticationExtensionsClientOutputs,
-    CredentialProtectionPolicy, HMACGetSecretInput, PublicKeyCredentialParameters,
-    PublicKeyCredentialUserEntity, RelyingParty, ResidentKeyRequirement,
-    UserVerificationRequirement,
+    AuthenticationExtensionsPRFInputs, AuthenticationExtensionsPRFOutputs,
+    AuthenticationExtensionsPRFValues, CredentialProtectionPolicy, HMACGetSecretInput,
+    PublicKeyCredentialDescriptor, PublicKeyCredentialParameters, PublicKeyCredentialUserEntity,
+    RelyingParty, ResidentKeyRequirement, Transport, UserVerificationRequirement,
   },
+  errors::{AuthenticatorError, HIDError},
   statecallback::StateCallback,
-  Pin, StatusPinUv, StatusUpdate,
+  StatusPinUv, StatusUpdate,
 };
 use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine};
 use base64urlsafedata::Base64UrlSafeData;
 use openssl::sha::Sha256;
-use tauri::{async_runtime::block_on, AppHandle, Emitter, Runtime, Url};
-use tokio::sync::mpsc;
+use tauri::{AppHandle, Manager, Runtime, Url};
+use tokio::sync::{mpsc, oneshot};
 use webauthn_rs_proto::{
-  AuthenticatorTransport, CollectedClientData, PublicKeyCredential,
-  PublicKeyCredentialCreationOptions, PublicKeyCredentialRequestOptions,
+  AttestationConveyancePreference, AuthenticatorTransport, CollectedClientData,
+  PublicKeyCredential, PublicKeyCredentialCreationOptions, PublicKeyCredentialRequestOptions,
   RegisterPublicKeyCredential, RegistrationExtensionsClientOutputs,
   RequestAuthenticationExtensions, RequestRegistrationExtensions,
 };
 
-use crate::authenticators::ctap2::event::WebauthnEvent;
-
-use super::EVENT_NAME;
+use crate::auth_data::{AttestedCredentialData, AuthenticatorData};
+use crate::authenticators::ctap2::{
+  event::{self, WebauthnEvent},
+  management::Message,
+  pending::PendingRequests,
+};
+use crate::events::EventSink;
+use crate::extensions::{
+  ExtensionInputs, ExtensionOutputs, PrfInputs, PrfOutputs, PrfValues, WithExtensions,
+};
+use crate::operation::Operations;
+use crate::Config;
 
-pub fn init_manager() -> crate::Result<AuthenticatorService> {
+pub fn init_manager(config: &Config) -> crate::Result<AuthenticatorService> {
   let mut manager = AuthenticatorService::new()?;
-  manager.add_u2f_usb_hid_platform_transports();
+  if config.transport_enabled(&AuthenticatorTransport::Usb) {
+    manager.add_u2f_usb_hid_platform_transports();
+  }
   Ok(manager)
 }
 
 pub trait AuthenticatorExt {
-  fn perform_register(
-    &mut self,
+  async fn perform_register(
+    &self,
     status_tx: Sender<StatusUpdate>,
     url: Url,
     options: PublicKeyCredentialCreationOptions,
+    extensions: ExtensionInputs,
     timeout: u64,
-  ) -> crate::Result<RegisterPublicKeyCredential>;
+    ctap1_fallback: bool,
+  ) -> crate::Result<WithExtensions<RegisterPublicKeyCredential, ExtensionOutputs>>;
 
-  fn perform_authentication(
-    &mut self,
+  async fn perform_authentication(
+    &self,
     status_tx: Sender<StatusUpdate>,
     url: Url,
     options: PublicKeyCredentialRequestOptions,
+    extensions: ExtensionInputs,
     timeout: u64,
-  ) -> crate::Result<PublicKeyCredential>;
+  ) -> crate::Result<WithExtensions<PublicKeyCredential, ExtensionOutputs>>;
+
+  async fn perform_reset(&self, status_tx: Sender<StatusUpdate>, timeout: u64)
+    -> crate::Result<()>;
 }
 
-impl AuthenticatorExt for AuthenticatorService {
-  fn perform_register(
-    &mut self,
+/// The lock on the service is only held while a transaction is started, the result is awaited without blocking.
+impl AuthenticatorExt for Mutex<AuthenticatorService> {
+  async fn perform_register(
+    &self,
     status_tx: Sender<StatusUpdate>,
     url: Url,
     options: PublicKeyCredentialCreationOptions,
+    extensions: ExtensionInputs,
     timeout: u64,
-  ) -> crate::Result<RegisterPublicKeyCredential> {
+    ctap1_fallback: bool,
+  ) -> crate::Result<WithExtensions<RegisterPublicKeyCredential, ExtensionOutputs>> {
+    let attestation = options.attestation.unwrap_or_default();
+
     let client_data: Vec<u8> = serde_json::to_vec(&CollectedClientData {
       type_: "webauthn.create".to_string(),
       challenge: options.challenge,
@@ -76,11 +105,22 @@ impl AuthenticatorExt for AuthenticatorService {
     hasher.update(&client_data);
     let client_data_hash = hasher.finish();
 
+    let (user_verification_req, resident_key_req) = match options.authenticator_selection {
+      Some(selection) => (
+        convert_user_verification(selection.user_verification),
+        convert_resident_key(selection.resident_key, selection.require_resident_key),
+      ),
+      None => (
+        UserVerificationRequirement::Preferred,
+        ResidentKeyRequirement::Discouraged,
+      ),
+    };
+
     let args = RegisterArgs {
       pin: None,
       client_data_hash,
       origin: url.to_string(),
-      user_verification_req: UserVerificationRequirement::Required,
+      user_verification_req,
       use_ctap1_fallback: false,
       relying_party: RelyingParty {
         id: options.rp.id,
@@ -91,49 +131,91 @@ impl AuthenticatorExt for AuthenticatorService {
         name: Some(options.user.name),
         display_name: Some(options.user.display_name),
       },
-      exclude_list: Vec::new(),
-      resident_key_req: ResidentKeyRequirement::Required,
-      extensions: convert_request_registration_extensions(options.extensions),
+      exclude_list: options
+        .exclude_credentials
+        .unwrap_or_default()
+        .into_iter()
+        .map(|c| convert_credential_descriptor(c.id, c.transports))
+        .collect(),
+      resident_key_req,
+      extensions: convert_request_registration_extensions(options.extensions, extensions)?,
       pub_cred_params: convert_algorithms(options.pub_key_cred_params),
     };
 
-    let (register_tx, register_rx) = channel();
-    let callback = StateCallback::new(Box::new(move |rv| register_tx.send(rv).unwrap()));
+    let (register_tx, register_rx) = oneshot::channel();
+    let callback = StateCallback::new(Box::new(move |rv| {
+      let _ = register_tx.send(rv);
+    }));
 
     #[cfg(feature = "log")]
     log::debug!("Registering with args: {args:?}");
 
-    self.register(timeout, args, status_tx, callback)?;
-    let result = register_rx.recv().unwrap()?;
+    self
+      .lock()
+      .unwrap()
+      .register(timeout, args, status_tx, callback)?;
+    let mut result = register_rx.await.map_err(|_| crate::Error::Aborted)??;
 
     #[cfg(feature = "log")]
     log::debug!("Register result: {result:?}");
 
-    Ok(webauthn_rs_proto::RegisterPublicKeyCredential {
-      extensions: convert_response_registration_extensions(result.extensions),
+    // Connected CTAP1 keys are refused before the ceremony, this catches keys which can't be
+    // enumerated (macOS) from the U2F attestation format the authenticator service falls back to.
+    if !ctap1_fallback && matches!(result.att_obj.att_stmt, AttestationStatement::FidoU2F(_)) {
+      return Err(crate::Error::Ctap1NotAllowed);
+    }
+    // there is no anonymization CA, so `indirect` is conveyed unaltered like `direct`
+    if matches!(attestation, AttestationConveyancePreference::None)
+      && !is_self_attestation(&result.att_obj)
+    {
+      result.att_obj.anonymize();
+    }
+    let auth_data = convert_authenticator_data(&result.att_obj.auth_data)?;
+
+    let raw_id = result
+      .att_obj
+      .auth_data
+      .credential_data
+      .as_ref()
+      .map(|c| c.credential_id.clone())
+      .ok_or(crate::Error::InvalidResponse)?;
+
+    let att_stmt = serde_cbor_2::value::to_value(&result.att_obj.att_stmt)?;
+    let attestation_object =
+      auth_data.attestation_object(result.att_obj.att_stmt.id(), &att_stmt)?;
+
+    let outputs = ExtensionOutputs {
+      prf: result.extensions.prf.clone().map(convert_prf_outputs),
+    };
+    let credential = webauthn_rs_proto::RegisterPublicKeyCredential {
+      extensions: convert_response_registration_extensions(
+        result.extensions,
+        &result.att_obj.auth_data.extensions,
+      ),
       response: webauthn_rs_proto::AuthenticatorAttestationResponseRaw {
-        attestation_object: serde_cbor_2::to_vec(&result.att_obj)?.into(),
+        attestation_object: attestation_object.into(),
         client_data_json: Base64UrlSafeData::from(client_data),
-        transports: Some(vec![
-          AuthenticatorTransport::Usb,
-          AuthenticatorTransport::Nfc,
-          AuthenticatorTransport::Internal,
-          AuthenticatorTransport::Ble,
-        ]),
+        // the authenticator service doesn't expose which key was used, it only talks to USB keys
+        transports: Some(vec![AuthenticatorTransport::Usb]),
       },
-      id: String::new(),
-      raw_id: Vec::new().into(),
+      id: BASE64_URL_SAFE_NO_PAD.encode(&raw_id),
+      raw_id: raw_id.into(),
       type_: "public-key".to_string(),
+    };
+    Ok(WithExtensions {
+      inner: credential,
+      extensions: outputs,
     })
   }
 
-  fn perform_authentication(
-    &mut self,
+  async fn perform_authentication(
+    &self,
     status_tx: Sender<StatusUpdate>,
     url: Url,
     options: PublicKeyCredentialRequestOptions,
+    extensions: ExtensionInputs,
     timeout: u64,
-  ) -> crate::Result<PublicKeyCredential> {
+  ) -> crate::Result<WithExtensions<PublicKeyCredential, ExtensionOutputs>> {
     let client_data: Vec<u8> = serde_json::to_vec(&CollectedClientData {
       type_: "webauthn.get".to_string(),
       challenge: options.challenge,
@@ -147,54 +229,107 @@ impl AuthenticatorExt for AuthenticatorService {
     hasher.update(&client_data);
     let client_data_hash = hasher.finish();
 
+    let allow_list: Vec<PublicKeyCredentialDescriptor> = options
+      .allow_credentials
+      .into_iter()
+      .map(|c| convert_credential_descriptor(c.id, c.transports))
+      .collect();
+    // the authenticator may omit the credential if the allow list contains exactly one entry
+    let single_allowed = match allow_list.as_slice() {
+      [credential] => Some(credential.id.clone()),
+      _ => None,
+    };
+
+    let extensions =
+      convert_request_authentication_extensions(options.extensions, extensions, &allow_list)?;
     let args = SignArgs {
       pin: None,
       relying_party_id: options.rp_id.clone(),
       client_data_hash,
       origin: url.to_string(),
       user_presence_req: true,
-      user_verification_req: UserVerificationRequirement::Required,
+      user_verification_req: convert_user_verification(options.user_verification),
       use_ctap1_fallback: false,
-      allow_list: Vec::new(),
-      extensions: convert_request_authentication_extensions(options.extensions),
+      allow_list,
+      extensions,
     };
 
-    let (sign_tx, sign_rx) = channel();
+    let (sign_tx, sign_rx) = oneshot::channel();
     let callback = StateCallback::new(Box::new(move |rv| {
-      sign_tx.send(rv).unwrap();
+      let _ = sign_tx.send(rv);
     }));
 
     #[cfg(feature = "log")]
     log::debug!("Signing with args: {args:?}");
 
-    self.sign(timeout, args, status_tx, callback)?;
-    let result = sign_rx.recv().unwrap()?;
+    self
+      .lock()
+      .unwrap()
+      .sign(timeout, args, status_tx, callback)?;
+    let result = sign_rx.await.map_err(|_| crate::Error::Aborted)??;
 
     #[cfg(feature = "log")]
     log::debug!("Sign result: {result:?}");
 
-    let raw_id = result.assertion.credentials.unwrap().id;
-    let data = serde_cbor_2::to_vec(&result.assertion.auth_data)?;
+    let raw_id = result
+      .assertion
+      .credentials
+      .map(|c| c.id)
+      .or(single_allowed)
+      .ok_or(crate::Error::NoCredential)?;
+    let auth_data = convert_authenticator_data(&result.assertion.auth_data)?;
 
-    Ok(PublicKeyCredential {
+    let outputs = ExtensionOutputs {
+      prf: result.extensions.prf.clone().map(convert_prf_outputs),
+    };
+    let credential = PublicKeyCredential {
       id: BASE64_URL_SAFE_NO_PAD.encode(&raw_id),
       raw_id: raw_id.into(),
       type_: "public-key".to_string(),
       response: webauthn_rs_proto::AuthenticatorAssertionResponseRaw {
         client_data_json: Base64UrlSafeData::from(client_data),
-        authenticator_data: data[2..].into(),
+        authenticator_data: auth_data.to_bytes()?.into(),
         signature: result.assertion.signature.into(),
         user_handle: result.assertion.user.map(|h| h.id.into()),
       },
       extensions: convert_response_authentication_extensions(result.extensions),
+    };
+    Ok(WithExtensions {
+      inner: credential,
+      extensions: outputs,
     })
   }
+
+  async fn perform_reset(
+    &self,
+    status_tx: Sender<StatusUpdate>,
+    timeout: u64,
+  ) -> crate::Result<()> {
+    let (reset_tx, reset_rx) = oneshot::channel();
+    let callback = StateCallback::new(Box::new(move |rv| {
+      let _ = reset_tx.send(rv);
+    }));
+
+    self.lock().unwrap().reset(timeout, status_tx, callback)?;
+    match reset_rx.await.map_err(|_| crate::Error::Aborted)? {
+      // the key was plugged in for too long, CTAP2 only allows a reset within 10 seconds after power-up
+      Err(AuthenticatorError::HIDError(HIDError::Command(CommandError::StatusCode(
+        StatusCode::NotAllowed,
+        _,
+      )))) => Err(crate::Error::ResetWindowExpired),
+      result => Ok(result?),
+    }
+  }
 }
 
+/// Starts a thread forwarding the status updates of one ceremony to `events`.
+/// Updates of a management session are forwarded to `management` instead.
+/// The thread ends once the authenticator service drops the returned sender.
 pub fn status<R: Runtime>(
   app_handle: AppHandle<R>,
-  pin_sender: mpsc::Sender<Sender<Pin>>,
-  select_sender: mpsc::Sender<Sender<Option<usize>>>,
+  pending: Arc<PendingRequests>,
+  events: EventSink,
+  management: Option<mpsc::UnboundedSender<Message>>,
 ) -> Sender<StatusUpdate> {
   let (status_tx, status_rx) = channel::<StatusUpdate>();
   thread::spawn(move || loop {
@@ -205,27 +340,91 @@ pub fn status<R: Runtime>(
     #[cfg(feature = "log")]
     log::debug!("Status: {status:?}");
 
-    match &status {
+    if let StatusUpdate::InteractiveManagement(update) = status {
+      if let Some(management) = &management {
+        let _ = management.send(Message::Update(update));
+      }
+      continue;
+    }
+
+    if let EventSink::Handler(handler) = &events {
+      event::handle(handler.as_ref(), status);
+      continue;
+    }
+
+    let operations = app_handle.try_state::<Operations>();
+    let request_id = match &status {
       StatusUpdate::PinUvError(StatusPinUv::PinRequired(sender))
       | StatusUpdate::PinUvError(StatusPinUv::InvalidPin(sender, ..)) => {
-        block_on(async {
-          let _ = pin_sender.send(sender.clone()).await;
-        });
+        if let Some(operations) = &operations {
+          operations.awaiting_pin();
+        }
+        Some(pending.request_pin(sender.clone()))
       }
       StatusUpdate::SelectResultNotice(sender, ..) => {
-        block_on(async {
-          let _ = select_sender.send(sender.clone()).await;
-        });
+        if let Some(operations) = &operations {
+          operations.awaiting_selection();
+        }
+        Some(pending.request_selection(sender.clone()))
       }
-      _ => (),
-    }
+      _ => None,
+    };
 
-    let event: WebauthnEvent = status.into();
-    let _ = app_handle.emit(EVENT_NAME, event);
+    match WebauthnEvent::new(status, request_id) {
+      Some(event) => event.send(&app_handle, &events),
+      None => {
+        #[cfg(feature = "log")]
+        log::debug!("Ignoring status update outside of a ceremony");
+      }
+    }
   });
   status_tx
 }
 
+/// Self attestation doesn't identify the key, so it is conveyed even if no attestation was requested.
+/// See <https://www.w3.org/TR/webauthn-3/#dom-attestationconveyancepreference-none>.
+fn is_self_attestation(att_obj: &attestation::AttestationObject) -> bool {
+  let AttestationStatement::Packed(statement) = &att_obj.att_stmt else {
+    return false;
+  };
+  statement.attestation_cert.is_empty()
+    && att_obj
+      .auth_data
+      .credential_data
+      .as_ref()
+      .is_some_and(|credential| credential.aaguid.0 == [0; 16])
+}
+
+/// Encodes the authenticator data the authenticator service parsed, the raw bytes signed by the key aren't exposed.
+/// Extensions unknown to the authenticator service are lost.
+fn convert_authenticator_data(
+  auth_data: &attestation::AuthenticatorData,
+) -> crate::Result<AuthenticatorData> {
+  Ok(AuthenticatorData {
+    rp_id_hash: auth_data.rp_id_hash.0,
+    flags: auth_data.flags.bits(),
+    sign_count: auth_data.counter,
+    attested_credential_data: auth_data
+      .credential_data
+      .as_ref()
+      .map(|credential| -> crate::Result<_> {
+        Ok(AttestedCredentialData {
+          aaguid: credential.aaguid.0,
+          credential_id: credential.credential_id.clone(),
+          credential_public_key: serde_cbor_2::to_vec(&credential.credential_public_key)?,
+        })
+      })
+      .transpose()?,
+    // the flag is also set by keys which return an empty map
+    extensions: (auth_data.extensions.has_some()
+      || auth_data
+        .flags
+        .contains(attestation::AuthenticatorDataFlags::EXTENSION_DATA))
+    .then(|| serde_cbor_2::to_vec(&auth_data.extensions))
+    .transpose()?,
+  })
+}
+
 fn convert_response_authentication_extensions(
   extensions: AuthenticationExtensionsClientOutputs,
 ) -> webauthn_rs_proto::AuthenticationExtensionsClientOutputs {
@@ -242,41 +441,141 @@ fn convert_response_authentication_extensions(
 
 fn convert_request_authentication_extensions(
   extensions: Option<RequestAuthenticationExtensions>,
-) -> AuthenticationExtensionsClientInputs {
-  extensions
-    .map(|e| AuthenticationExtensionsClientInputs {
-      app_id: e.appid,
-      hmac_get_secret: e.hmac_get_secret.map(|h| HMACGetSecretInput {
-        salt1: h.output1.to_vec().try_into().unwrap(),
-        salt2: h.output2.map(|s| s.to_vec().try_into().unwrap()),
-      }),
-      ..Default::default()
+  additional: ExtensionInputs,
+  allow_list: &[PublicKeyCredentialDescriptor],
+) -> crate::Result<AuthenticationExtensionsClientInputs> {
+  let (app_id, hmac_get_secret) = extensions
+    .map(|e| (e.appid, e.hmac_get_secret))
+    .unwrap_or_default();
+  let hmac_get_secret = hmac_get_secret
+    .map(|h| -> crate::Result<_> {
+      Ok(HMACGetSecretInput {
+        salt1: convert_salt(h.output1)?,
+        salt2: h.output2.map(convert_salt).transpose()?,
+      })
     })
-    .unwrap_or_default()
+    .transpose()?;
+  if hmac_get_secret.is_some() && additional.prf.is_some() {
+    return Err(crate::Error::InvalidExtensionInput(
+      "hmacGetSecret and prf can not be used together",
+    ));
+  }
+  Ok(AuthenticationExtensionsClientInputs {
+    app_id,
+    hmac_get_secret,
+    prf: additional
+      .prf
+      .map(|prf| convert_prf_inputs(prf, Some(allow_list)))
+      .transpose()?,
+    ..Default::default()
+  })
 }
 
 fn convert_request_registration_extensions(
   extensions: Option<RequestRegistrationExtensions>,
-) -> AuthenticationExtensionsClientInputs {
-  extensions
-    .map(|e| AuthenticationExtensionsClientInputs {
-      cred_props: e.cred_props,
-      min_pin_length: e.min_pin_length,
-      hmac_create_secret: e.hmac_create_secret,
-      credential_protection_policy: e
-        .cred_protect
-        .clone()
-        .map(|c| convert_credential_protection_policy(c.credential_protection_policy)),
-      enforce_credential_protection_policy: e
-        .cred_protect
-        .and_then(|c| c.enforce_credential_protection_policy),
+  additional: ExtensionInputs,
+) -> crate::Result<AuthenticationExtensionsClientInputs> {
+  let prf = additional
+    .prf
+    .map(|prf| convert_prf_inputs(prf, None))
+    .transpose()?;
+  let Some(e) = extensions else {
+    return Ok(AuthenticationExtensionsClientInputs {
+      prf,
       ..Default::default()
-    })
-    .unwrap_or_default()
+    });
+  };
+  if e.hmac_create_secret.is_some() && prf.is_some() {
+    return Err(crate::Error::InvalidExtensionInput(
+      "hmacCreateSecret and prf can not be used together",
+    ));
+  }
+  Ok(AuthenticationExtensionsClientInputs {
+    cred_props: e.cred_props,
+    min_pin_length: e.min_pin_length,
+    hmac_create_secret: e.hmac_create_secret,
+    credential_protection_policy: e
+      .cred_protect
+      .clone()
+      .map(|c| convert_credential_protection_policy(c.credential_protection_policy)),
+    enforce_credential_protection_policy: e
+      .cred_protect
+      .and_then(|c| c.enforce_credential_protection_policy),
+    prf,
+    ..Default::default()
+  })
 }
 
+/// `hmac-secret` salts have to be exactly 32 bytes.
+fn convert_salt(salt: Base64UrlSafeData) -> crate::Result<[u8; 32]> {
+  salt
+    .to_vec()
+    .try_into()
+    .map_err(|_| crate::Error::InvalidExtensionInput("hmacGetSecret salts must be 32 bytes long"))
+}
+
+/// Validates the `prf` inputs, the salts are hashed by the authenticator service.
+/// `allow_list` is `None` during registration, where `evalByCredential` is not allowed.
+fn convert_prf_inputs(
+  prf: PrfInputs,
+  allow_list: Option<&[PublicKeyCredentialDescriptor]>,
+) -> crate::Result<AuthenticationExtensionsPRFInputs> {
+  let eval_by_credential = match allow_list {
+    _ if prf.eval_by_credential.is_empty() => None,
+    None => {
+      return Err(crate::Error::InvalidExtensionInput(
+        "evalByCredential is only allowed during authentication",
+      ))
+    }
+    Some([]) => {
+      return Err(crate::Error::InvalidExtensionInput(
+        "evalByCredential requires allowCredentials",
+      ))
+    }
+    Some(allow_list) => Some(
+      prf
+        .eval_by_credential
+        .into_iter()
+        .map(|(id, values)| {
+          let id = BASE64_URL_SAFE_NO_PAD
+            .decode(&id)
+            .ok()
+            .filter(|id| allow_list.iter().any(|c| &c.id == id))
+            .ok_or(crate::Error::InvalidExtensionInput(
+              "evalByCredential keys must be base64url encoded ids of allowCredentials",
+            ))?;
+          Ok((id, convert_prf_values(values)))
+        })
+        .collect::<crate::Result<_>>()?,
+    ),
+  };
+  Ok(AuthenticationExtensionsPRFInputs {
+    eval: prf.eval.map(convert_prf_values),
+    eval_by_credential,
+  })
+}
+
+fn convert_prf_values(values: PrfValues) -> AuthenticationExtensionsPRFValues {
+  AuthenticationExtensionsPRFValues {
+    first: values.first.into(),
+    second: values.second.map(Into::into),
+  }
+}
+
+fn convert_prf_outputs(prf: AuthenticationExtensionsPRFOutputs) -> PrfOutputs {
+  PrfOutputs {
+    enabled: prf.enabled,
+    results: prf.results.map(|results| PrfValues {
+      first: results.first.into(),
+      second: results.second.map(Into::into),
+    }),
+  }
+}
+
+/// `credProtect` and `minPinLength` are only reported in the authenticator data.
 fn convert_response_registration_extensions(
   extensions: AuthenticationExtensionsClientOutputs,
+  auth_data: &Extension,
 ) -> RegistrationExtensionsClientOutputs {
   RegistrationExtensionsClientOutputs {
     appid: extensions.app_id,
@@ -284,7 +583,28 @@ fn convert_response_registration_extensions(
     cred_props: extensions
       .cred_props
       .map(|c| webauthn_rs_proto::CredProps { rk: Some(c.rk) }),
-    ..Default::default()
+    cred_protect: auth_data
+      .cred_protect
+      .map(convert_response_credential_protection_policy),
+    min_pin_length: auth_data
+      .min_pin_length
+      .and_then(|length| length.try_into().ok()),
+  }
+}
+
+fn convert_response_credential_protection_policy(
+  cred_protect: CredentialProtectionPolicy,
+) -> webauthn_rs_proto::CredentialProtectionPolicy {
+  match cred_protect {
+    CredentialProtectionPolicy::UserVerificationOptional => {
+      webauthn_rs_proto::CredentialProtectionPolicy::UserVerificationOptional
+    }
+    CredentialProtectionPolicy::UserVerificationOptionalWithCredentialIDList => {
+      webauthn_rs_proto::CredentialProtectionPolicy::UserVerificationOptionalWithCredentialIDList
+    }
+    CredentialProtectionPolicy::UserVerificationRequired => {
+      webauthn_rs_proto::CredentialProtectionPolicy::UserVerificationRequired
+    }
   }
 }
 
@@ -304,6 +624,58 @@ fn convert_credential_protection_policy(
   }
 }
 
+fn convert_user_verification(
+  user_verification: webauthn_rs_proto::UserVerificationPolicy,
+) -> UserVerificationRequirement {
+  match user_verification {
+    webauthn_rs_proto::UserVerificationPolicy::Required => UserVerificationRequirement::Required,
+    webauthn_rs_proto::UserVerificationPolicy::Preferred => UserVerificationRequirement::Preferred,
+    webauthn_rs_proto::UserVerificationPolicy::Discouraged_DO_NOT_USE => {
+      UserVerificationRequirement::Discouraged
+    }
+  }
+}
+
+/// `residentKey` takes precedence over the legacy `requireResidentKey` flag if it is present.
+fn convert_resident_key(
+  resident_key: Option<webauthn_rs_proto::ResidentKeyRequirement>,
+  require_resident_key: bool,
+) -> ResidentKeyRequirement {
+  match resident_key {
+    Some(webauthn_rs_proto::ResidentKeyRequirement::Required) => ResidentKeyRequirement::Required,
+    Some(webauthn_rs_proto::ResidentKeyRequirement::Preferred) => ResidentKeyRequirement::Preferred,
+    Some(webauthn_rs_proto::ResidentKeyRequirement::Discouraged) => {
+      ResidentKeyRequirement::Discouraged
+    }
+    None if require_resident_key => ResidentKeyRequirement::Required,
+    None => ResidentKeyRequirement::Discouraged,
+  }
+}
+
+fn convert_credential_descriptor(
+  id: Base64UrlSafeData,
+  transports: Option<Vec<AuthenticatorTransport>>,
+) -> PublicKeyCredentialDescriptor {
+  PublicKeyCredentialDescriptor {
+    id: id.into(),
+    transports: transports
+      .unwrap_or_default()
+      .into_iter()
+      .filter_map(convert_transport)
+      .collect(),
+  }
+}
+
+fn convert_transport(transport: AuthenticatorTransport) -> Option<Transport> {
+  match transport {
+    AuthenticatorTransport::Usb => Some(Transport::USB),
+    AuthenticatorTransport::Nfc => Some(Transport::NFC),
+    AuthenticatorTransport::Ble => Some(Transport::BLE),
+    AuthenticatorTransport::Internal => Some(Transport::Internal),
+    _ => None,
+  }
+}
+
 fn convert_algorithms(
   algorithms: Vec<webauthn_rs_proto::PubKeyCredParams>,
 ) -> Vec<PublicKeyCredentialParameters> {
@@ -0,0 +1,81 @@
+use std::{
+  sync::Mutex,
+  time::{Duration, Instant},
+};
+
+use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine};
+
+use crate::Error;
+
+/// How long a confirmation token can be used.
+const TOKEN_LIFETIME: Duration = Duration::from_secs(60);
+
+/// Confirmation of a factory reset.
+/// A reset deletes all credentials of the security key, so it has to be confirmed
+/// with the single use token returned by [`ResetConfirmation::issue`], by the caller it was issued to.
+pub struct ResetConfirmation {
+  token: Mutex<Option<IssuedToken>>,
+}
+
+struct IssuedToken {
+  token: String,
+  owner: String,
+  expires: Instant,
+}
+
+impl ResetConfirmation {
+  pub fn new() -> Self {
+    ResetConfirmation {
+      token: Mutex::new(None),
+    }
+  }
+
+  /// Creates a new token for `owner`, replacing any previous one.
+  pub fn issue(&self, owner: &str) -> crate::Result<String> {
+    let mut bytes = [0; 16];
+    openssl::rand::rand_bytes(&mut bytes)?;
+    let token = BASE64_URL_SAFE_NO_PAD.encode(bytes);
+    *self.token.lock().unwrap() = Some(IssuedToken {
+      token: token.clone(),
+      owner: owner.to_string(),
+      expires: Instant::now() + TOKEN_LIFETIME,
+    });
+    Ok(token)
+  }
+
+  /// Consumes the token, fails if it does not match the latest issued one, was issued to another owner or has expired.
+  pub fn confirm(&self, token: &str, owner: &str) -> crate::Result<()> {
+    match self.token.lock().unwrap().take() {
+      Some(issued)
+        if issued.token == token && issued.owner == owner && Instant::now() < issued.expires =>
+      {
+        Ok(())
+      }
+      _ => Err(Error::ResetNotConfirmed),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn confirms_token_of_owner() {
+    let reset = ResetConfirmation::new();
+    let token = reset.issue("main").unwrap();
+    assert!(reset.confirm(&token, "main").is_ok());
+    // single use
+    assert!(reset.confirm(&token, "main").is_err());
+  }
+
+  #[test]
+  fn rejects_token_of_other_owner() {
+    let reset = ResetConfirmation::new();
+    let token = reset.issue("main").unwrap();
+    assert!(reset.confirm(&token, "other").is_err());
+    let stale = reset.issue("main").unwrap();
+    reset.issue("main").unwrap();
+    assert!(reset.confirm(&stale, "main").is_err());
+  }
+}
@@ -1,4 +1,3 @@
-use serde::de::DeserializeOwned;
 use tauri::{
   plugin::{PluginApi, PluginHandle},
   AppHandle, Runtime, Url,
@@ -9,6 +8,7 @@ use webauthn_rs_proto::{
 };
 
 use super::Authenticator;
+use crate::{events::EventSink, Config};
 
 #[cfg(target_os = "ios")]
 tauri::ios_plugin_binding!(init_plugin_webauthn);
@@ -17,7 +17,11 @@ tauri::ios_plugin_binding!(init_plugin_webauthn);
 pub struct Webauthn<R: Runtime>(PluginHandle<R>);
 
 impl<R: Runtime> Authenticator<R> for Webauthn<R> {
-  fn init<C: DeserializeOwned>(_app: &AppHandle<R>, api: PluginApi<R, C>) -> crate::Result<Self> {
+  fn init(
+    _app: &AppHandle<R>,
+    api: PluginApi<R, Option<Config>>,
+    _config: &Config,
+  ) -> crate::Result<Self> {
     #[cfg(target_os = "android")]
     let handle = api.register_android_plugin("de.plugin.webauthn", "WebauthnPlugin")?;
     #[cfg(target_os = "ios")]
@@ -25,11 +29,12 @@ impl<R: Runtime> Authenticator<R> for Webauthn<R> {
     Ok(Webauthn(handle))
   }
 
-  fn register(
+  async fn register(
     &self,
     _origin: Url,
     mut options: PublicKeyCredentialCreationOptions,
     _timeout: u32,
+    _events: EventSink,
   ) -> crate::Result<RegisterPublicKeyCredential> {
     // This is required to make Android save the passkey
     if let Some(auth) = &mut options.authenticator_selection {
@@ -37,19 +42,22 @@ impl<R: Runtime> Authenticator<R> for Webauthn<R> {
     }
     self
       .0
-      .run_mobile_plugin("register", serde_json::to_string(&options)?)
+      .run_mobile_plugin_async("register", serde_json::to_string(&options)?)
+      .await
       .map_err(Into::into)
   }
 
-  fn authenticate(
+  async fn authenticate(
     &self,
     _origin: Url,
     options: PublicKeyCredentialRequestOptions,
     _timeout: u32,
+    _events: EventSink,
   ) -> crate::Result<PublicKeyCredential> {
     self
       .0
-      .run_mobile_plugin("authenticate", serde_json::to_string(&options)?)
+      .run_mobile_plugin_async("authenticate", serde_json::to_string(&options)?)
+      .await
       .map_err(Into::into)
   }
 }
@@ -1,51 +1,286 @@
-use serde::de::DeserializeOwned;
+use std::future::Future;
+
 use tauri::{plugin::PluginApi, AppHandle, Runtime, Url};
 use webauthn_rs_proto::{
   PublicKeyCredential, PublicKeyCredentialCreationOptions, PublicKeyCredentialRequestOptions,
   RegisterPublicKeyCredential,
 };
 
+use crate::{
+  events::EventSink,
+  extensions::{ExtensionInputs, ExtensionOutputs, WithExtensions},
+  AuthenticatorSettings, Config, ConfigChange, CredentialList, CredentialUser, Enrollment,
+  FingerprintSensorInfo, PinInfo,
+};
+
+#[cfg(feature = "virtual-authenticator")]
+pub mod backend;
 #[cfg(not(any(target_os = "android", target_os = "ios", target_os = "windows")))]
 pub mod ctap2;
 #[cfg(mobile)]
 pub mod mobile;
+#[cfg(feature = "virtual-authenticator")]
+pub mod virtual_authenticator;
 #[cfg(all(desktop, windows))]
 pub mod windows;
 
+#[cfg(not(any(target_os = "android", target_os = "ios", target_os = "windows")))]
+pub type Platform<R> = ctap2::Webauthn<R>;
+#[cfg(all(desktop, windows))]
+pub type Platform<R> = windows::Webauthn<R>;
+#[cfg(mobile)]
+pub type Platform<R> = mobile::Webauthn<R>;
+
+/// A WebAuthn backend.
+/// The security key management methods, from `pin_info` to `reset`, are only supported by the CTAP2 backend,
+/// the default implementations reject them as unsupported.
 pub trait Authenticator<R: Runtime>: Sized {
-  fn init<C: DeserializeOwned>(app: &AppHandle<R>, api: PluginApi<R, C>) -> crate::Result<Self>;
+  fn init(
+    app: &AppHandle<R>,
+    api: PluginApi<R, Option<Config>>,
+    config: &Config,
+  ) -> crate::Result<Self>;
   /// Register a new webauthn credential.
-  /// This is a blocking call and should be run in a separate thread.
+  /// The future completes once the user finished the interaction with the authenticator.
+  /// Status updates during the ceremony are delivered to `events`.
   fn register(
     &self,
     origin: Url,
     options: PublicKeyCredentialCreationOptions,
     timeout: u32,
-  ) -> crate::Result<RegisterPublicKeyCredential>;
+    events: EventSink,
+  ) -> impl Future<Output = crate::Result<RegisterPublicKeyCredential>> + Send;
 
   /// Authenticate using webauthn.
-  /// This is a blocking call and should be run in a separate thread.
+  /// The future completes once the user finished the interaction with the authenticator.
+  /// Status updates during the ceremony are delivered to `events`.
   fn authenticate(
     &self,
     origin: Url,
     options: PublicKeyCredentialRequestOptions,
     timeout: u32,
-  ) -> crate::Result<PublicKeyCredential>;
+    events: EventSink,
+  ) -> impl Future<Output = crate::Result<PublicKeyCredential>> + Send;
 
-  /// Send a PIN to the authenticator.
+  /// Register a new webauthn credential with client extensions webauthn-rs-proto has no fields for, e.g. `prf`.
+  /// Authenticators without support ignore these extensions, like a browser ignores unknown extensions.
+  fn register_with_extensions(
+    &self,
+    origin: Url,
+    options: PublicKeyCredentialCreationOptions,
+    extensions: ExtensionInputs,
+    timeout: u32,
+    events: EventSink,
+  ) -> impl Future<Output = crate::Result<WithExtensions<RegisterPublicKeyCredential, ExtensionOutputs>>>
+       + Send
+  where
+    Self: Sync,
+  {
+    let supported = check_extensions(&extensions);
+    ignore_extensions(&extensions);
+    async move {
+      supported?;
+      self
+        .register(origin, options, timeout, events)
+        .await
+        .map(Into::into)
+    }
+  }
+
+  /// Authenticate using webauthn with client extensions webauthn-rs-proto has no fields for, e.g. `prf`.
+  /// Authenticators without support ignore these extensions, like a browser ignores unknown extensions.
+  fn authenticate_with_extensions(
+    &self,
+    origin: Url,
+    options: PublicKeyCredentialRequestOptions,
+    extensions: ExtensionInputs,
+    timeout: u32,
+    events: EventSink,
+  ) -> impl Future<Output = crate::Result<WithExtensions<PublicKeyCredential, ExtensionOutputs>>> + Send
+  where
+    Self: Sync,
+  {
+    let supported = check_extensions(&extensions);
+    ignore_extensions(&extensions);
+    async move {
+      supported?;
+      self
+        .authenticate(origin, options, timeout, events)
+        .await
+        .map(Into::into)
+    }
+  }
+
+  /// Send a PIN for the request `request_id` announced by an event.
   /// This is only required for some authenticators.
-  fn send_pin(&self, pin: String) {
+  fn send_pin(&self, request_id: u64, pin: String) -> crate::Result<()> {
     #[cfg(feature = "log")]
     log::warn!("send_pin is not implemented/required for this authenticator");
     let _ = pin;
+    Err(crate::Error::RequestNotPending(request_id))
   }
 
-  /// Select a key from the authenticator.
+  /// Select a key for the request `request_id` announced by an event.
   /// This is only required for some authenticators.
-  fn select_key(&self, key: usize) {
+  fn select_key(&self, request_id: u64, key: usize) -> crate::Result<()> {
     #[cfg(feature = "log")]
     log::warn!("select_key is not implemented/required for this authenticator");
     let _ = key;
+    Err(crate::Error::RequestNotPending(request_id))
+  }
+
+  /// Query whether the security key has a PIN and which PIN policy it enforces.
+  fn pin_info(
+    &self,
+    timeout: u32,
+    events: EventSink,
+  ) -> impl Future<Output = crate::Result<PinInfo>> + Send {
+    let _ = (timeout, events);
+    unsupported("PIN management")
+  }
+
+  /// Set the PIN of a security key which does not have one yet.
+  fn set_pin(
+    &self,
+    pin: String,
+    timeout: u32,
+    events: EventSink,
+  ) -> impl Future<Output = crate::Result<()>> + Send {
+    let _ = (pin, timeout, events);
+    unsupported("PIN management")
+  }
+
+  /// Change the PIN of a security key. If `current_pin` is wrong, the PIN is requested through `events`.
+  fn change_pin(
+    &self,
+    current_pin: String,
+    new_pin: String,
+    timeout: u32,
+    events: EventSink,
+  ) -> impl Future<Output = crate::Result<()>> + Send {
+    let _ = (current_pin, new_pin, timeout, events);
+    unsupported("PIN management")
+  }
+
+  /// List the discoverable credentials stored on a security key.
+  fn list_credentials(
+    &self,
+    timeout: u32,
+    events: EventSink,
+  ) -> impl Future<Output = crate::Result<CredentialList>> + Send {
+    let _ = (timeout, events);
+    unsupported("Credential management")
+  }
+
+  /// Delete a discoverable credential from a security key.
+  fn delete_credential(
+    &self,
+    credential_id: Vec<u8>,
+    timeout: u32,
+    events: EventSink,
+  ) -> impl Future<Output = crate::Result<()>> + Send {
+    let _ = (credential_id, timeout, events);
+    unsupported("Credential management")
+  }
+
+  /// Replace the user name and display name of a discoverable credential. The user id must not change.
+  fn update_credential_user(
+    &self,
+    credential_id: Vec<u8>,
+    user: CredentialUser,
+    timeout: u32,
+    events: EventSink,
+  ) -> impl Future<Output = crate::Result<()>> + Send {
+    let _ = (credential_id, user, timeout, events);
+    unsupported("Credential management")
+  }
+
+  /// Query the fingerprint sensor of a security key.
+  fn fingerprint_sensor_info(
+    &self,
+    timeout: u32,
+    events: EventSink,
+  ) -> impl Future<Output = crate::Result<FingerprintSensorInfo>> + Send {
+    let _ = (timeout, events);
+    unsupported("Bio enrollment")
+  }
+
+  /// List the fingerprints enrolled on a security key.
+  fn list_enrollments(
+    &self,
+    timeout: u32,
+    events: EventSink,
+  ) -> impl Future<Output = crate::Result<Vec<Enrollment>>> + Send {
+    let _ = (timeout, events);
+    unsupported("Bio enrollment")
+  }
+
+  /// Enroll a new fingerprint.
+  /// The user has to touch the sensor repeatedly, the feedback for every sample is delivered to `events`.
+  fn enroll_fingerprint(
+    &self,
+    name: Option<String>,
+    timeout: u32,
+    events: EventSink,
+  ) -> impl Future<Output = crate::Result<()>> + Send {
+    let _ = (name, timeout, events);
+    unsupported("Bio enrollment")
+  }
+
+  /// Rename an enrolled fingerprint.
+  fn rename_enrollment(
+    &self,
+    id: Vec<u8>,
+    name: String,
+    timeout: u32,
+    events: EventSink,
+  ) -> impl Future<Output = crate::Result<()>> + Send {
+    let _ = (id, name, timeout, events);
+    unsupported("Bio enrollment")
+  }
+
+  /// Remove an enrolled fingerprint.
+  fn delete_enrollment(
+    &self,
+    id: Vec<u8>,
+    timeout: u32,
+    events: EventSink,
+  ) -> impl Future<Output = crate::Result<()>> + Send {
+    let _ = (id, timeout, events);
+    unsupported("Bio enrollment")
+  }
+
+  /// Change the configuration of a security key, e.g. enforce user verification.
+  fn configure_authenticator(
+    &self,
+    change: ConfigChange,
+    timeout: u32,
+    events: EventSink,
+  ) -> impl Future<Output = crate::Result<AuthenticatorSettings>> + Send {
+    let _ = (change, timeout, events);
+    unsupported("Authenticator configuration")
+  }
+
+  /// Issue the confirmation token required by `reset`, only `owner` can use it.
+  /// The commands pass the label of the calling webview.
+  fn prepare_reset(&self, owner: &str) -> crate::Result<String> {
+    let _ = owner;
+    #[cfg(feature = "log")]
+    log::warn!("Reset is not supported by this authenticator");
+    Err(crate::Error::Unsupported("Reset"))
+  }
+
+  /// Reset a security key to its factory state, deleting all credentials and the PIN.
+  /// `confirmation` has to be the latest token returned by `prepare_reset` for the same `owner`.
+  fn reset(
+    &self,
+    confirmation: String,
+    owner: String,
+    timeout: u32,
+    events: EventSink,
+  ) -> impl Future<Output = crate::Result<()>> + Send {
+    let _ = (confirmation, owner, timeout, events);
+    unsupported("Reset")
   }
 
   /// Cancel the current operation.
@@ -55,3 +290,25 @@ pub trait Authenticator<R: Runtime>: Sized {
     log::warn!("cancel is not implemented/required for this authenticator");
   }
 }
+
+/// Rejects the extensions no backend supports.
+/// `largeBlob` needs a per-credential key and the `authenticatorLargeBlobs` command, which the `authenticator` crate lacks.
+fn check_extensions(extensions: &ExtensionInputs) -> crate::Result<()> {
+  match extensions.large_blob {
+    Some(_) => Err(crate::Error::Unsupported("largeBlob")),
+    None => Ok(()),
+  }
+}
+
+fn ignore_extensions(_extensions: &ExtensionInputs) {
+  #[cfg(feature = "log")]
+  if _extensions.prf.is_some() {
+    log::warn!("The prf extension is not supported by this authenticator");
+  }
+}
+
+fn unsupported<T: Send>(feature: &'static str) -> impl Future<Output = crate::Result<T>> + Send {
+  #[cfg(feature = "log")]
+  log::warn!("{feature} is not supported by this authenticator");
+  std::future::ready(Err(crate::Error::Unsupported(feature)))
+}
@@ -0,0 +1,115 @@
+use std::collections::BTreeMap;
+
+use openssl::{
+  bn::{BigNum, BigNumContext},
+  ec::{EcGroup, EcKey},
+  hash::MessageDigest,
+  nid::Nid,
+  pkey::{PKey, Private},
+  rand::rand_bytes,
+  sign::Signer,
+};
+use serde_cbor_2::Value;
+use webauthn_rs_proto::User;
+
+pub const ES256: i64 = -7;
+pub const EDDSA: i64 = -8;
+
+const CREDENTIAL_ID_LENGTH: usize = 32;
+
+/// A credential created by the virtual authenticator.
+/// The private key never leaves the process and is lost once the app exits.
+pub struct Credential {
+  pub id: Vec<u8>,
+  pub rp_id: String,
+  pub user: User,
+  pub resident: bool,
+  pub sign_count: u32,
+  alg: i64,
+  key: PKey<Private>,
+}
+
+impl Credential {
+  /// Generates a new credential for the first supported algorithm in `algorithms`.
+  /// Returns `None` if none of the algorithms are supported.
+  pub fn generate(
+    algorithms: impl IntoIterator<Item = i64>,
+    rp_id: String,
+    user: User,
+    resident: bool,
+  ) -> crate::Result<Option<Self>> {
+    let Some(alg) = algorithms
+      .into_iter()
+      .find(|alg| matches!(*alg, ES256 | EDDSA))
+    else {
+      return Ok(None);
+    };
+
+    let key = match alg {
+      ES256 => {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+        PKey::from_ec_key(EcKey::generate(&group)?)?
+      }
+      _ => PKey::generate_ed25519()?,
+    };
+
+    let mut id = vec![0; CREDENTIAL_ID_LENGTH];
+    rand_bytes(&mut id)?;
+
+    Ok(Some(Credential {
+      id,
+      rp_id,
+      user,
+      resident,
+      sign_count: 0,
+      alg,
+      key,
+    }))
+  }
+
+  pub fn alg(&self) -> i64 {
+    self.alg
+  }
+
+  /// Encodes the public key as a COSE_Key.
+  pub fn cose_key(&self) -> crate::Result<Value> {
+    let mut map = BTreeMap::new();
+    map.insert(Value::Integer(3), Value::Integer(self.alg.into()));
+
+    if self.alg == ES256 {
+      let ec_key = self.key.ec_key()?;
+      let mut ctx = BigNumContext::new()?;
+      let mut x = BigNum::new()?;
+      let mut y = BigNum::new()?;
+      ec_key
+        .public_key()
+        .affine_coordinates(ec_key.group(), &mut x, &mut y, &mut ctx)?;
+
+      // kty: EC2, crv: P-256
+      map.insert(Value::Integer(1), Value::Integer(2));
+      map.insert(Value::Integer(-1), Value::Integer(1));
+      map.insert(Value::Integer(-2), Value::Bytes(x.to_vec_padded(32)?));
+      map.insert(Value::Integer(-3), Value::Bytes(y.to_vec_padded(32)?));
+    } else {
+      // kty: OKP, crv: Ed25519
+      map.insert(Value::Integer(1), Value::Integer(1));
+      map.insert(Value::Integer(-1), Value::Integer(6));
+      map.insert(Value::Integer(-2), Value::Bytes(self.key.raw_public_key()?));
+    }
+
+    Ok(Value::Map(map))
+  }
+
+  /// Signs `data` with the private key of this credential.
+  pub fn sign(&self, data: &[u8]) -> crate::Result<Vec<u8>> {
+    let signature = if self.alg == ES256 {
+      let mut signer = Signer::new(MessageDigest::sha256(), &self.key)?;
+      signer.update(data)?;
+      signer.sign_to_vec()?
+    } else {
+      let mut signer = Signer::new_without_digest(&self.key)?;
+      signer.sign_oneshot_to_vec(data)?
+    };
+    Ok(signature)
+  }
+}
@@ -0,0 +1,465 @@
+use std::{collections::BTreeMap, marker::PhantomData, sync::Mutex};
+
+use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine};
+use credential::Credential;
+use openssl::sha::sha256;
+use serde::Deserialize;
+use serde_cbor_2::Value;
+use tauri::{plugin::PluginApi, AppHandle, Runtime, Url};
+use webauthn_rs_proto::{
+  AttestationConveyancePreference, AuthenticatorTransport, CollectedClientData, CredProps,
+  PublicKeyCredential, PublicKeyCredentialCreationOptions, PublicKeyCredentialRequestOptions,
+  RegisterPublicKeyCredential, RegistrationExtensionsClientOutputs, ResidentKeyRequirement,
+  UserVerificationPolicy,
+};
+
+use super::Authenticator;
+use crate::auth_data::{AttestedCredentialData, AuthenticatorData};
+use crate::Error;
+use crate::{events::EventSink, Config};
+
+mod credential;
+
+/// Behaviour of the virtual authenticator.
+/// These options simulate the outcome of the user interaction that a real authenticator would require.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct VirtualAuthenticatorOptions {
+  /// Whether the simulated user confirms their presence (e.g. touches the key).
+  pub user_present: bool,
+  /// Whether the simulated user passes user verification (e.g. enters the correct PIN).
+  pub user_verified: bool,
+  /// The AAGUID reported in the attested credential data.
+  pub aaguid: [u8; 16],
+}
+
+impl Default for VirtualAuthenticatorOptions {
+  fn default() -> Self {
+    VirtualAuthenticatorOptions {
+      user_present: true,
+      user_verified: true,
+      aaguid: [0; 16],
+    }
+  }
+}
+
+/// Software authenticator that keeps all credentials in memory.
+/// Intended for automated tests on machines without a security key.
+pub struct Webauthn<R: Runtime> {
+  /// Whether the internal transport is enabled in the config.
+  enabled: bool,
+  options: Mutex<VirtualAuthenticatorOptions>,
+  credentials: Mutex<Vec<Credential>>,
+  phantom: PhantomData<AppHandle<R>>,
+}
+
+impl<R: Runtime> Webauthn<R> {
+  /// Replaces the options used for all following operations.
+  pub fn set_options(&self, options: VirtualAuthenticatorOptions) {
+    *self.options.lock().unwrap() = options;
+  }
+
+  /// Removes all credentials stored in the virtual authenticator.
+  pub fn clear_credentials(&self) {
+    self.credentials.lock().unwrap().clear();
+  }
+
+  /// Simulates the user interaction and returns whether the user was verified.
+  fn user_interaction(&self, policy: UserVerificationPolicy) -> crate::Result<bool> {
+    let options = self.options.lock().unwrap();
+    if !options.user_present {
+      return Err(Error::NotAllowed);
+    }
+    match policy {
+      UserVerificationPolicy::Required if !options.user_verified => Err(Error::NotAllowed),
+      UserVerificationPolicy::Discouraged_DO_NOT_USE => Ok(false),
+      _ => Ok(options.user_verified),
+    }
+  }
+}
+
+impl<R: Runtime> Authenticator<R> for Webauthn<R> {
+  fn init(
+    _app: &AppHandle<R>,
+    _api: PluginApi<R, Option<Config>>,
+    config: &Config,
+  ) -> crate::Result<Self> {
+    Ok(Webauthn {
+      enabled: config.transport_enabled(&AuthenticatorTransport::Internal),
+      options: Mutex::new(config.virtual_authenticator.clone()),
+      credentials: Mutex::new(Vec::new()),
+      phantom: PhantomData,
+    })
+  }
+
+  /// Register a new credential in the virtual authenticator.
+  async fn register(
+    &self,
+    origin: Url,
+    options: PublicKeyCredentialCreationOptions,
+    _timeout: u32,
+    _events: EventSink,
+  ) -> crate::Result<RegisterPublicKeyCredential> {
+    #[cfg(feature = "log")]
+    log::info!("Registering with options: {options:?}");
+    if !self.enabled {
+      return Err(Error::NoToken);
+    }
+    let mut credentials = self.credentials.lock().unwrap();
+
+    let excluded = options.exclude_credentials.unwrap_or_default();
+    if credentials
+      .iter()
+      .any(|c| c.rp_id == options.rp.id && excluded.iter().any(|e| e.id.as_slice() == c.id))
+    {
+      return Err(Error::CredentialExcluded);
+    }
+
+    let selection = options.authenticator_selection.unwrap_or_default();
+    let user_verified = self.user_interaction(selection.user_verification)?;
+    let resident = selection.require_resident_key
+      || matches!(
+        selection.resident_key,
+        Some(ResidentKeyRequirement::Preferred | ResidentKeyRequirement::Required)
+      );
+
+    let credential = Credential::generate(
+      options.pub_key_cred_params.iter().map(|p| p.alg),
+      options.rp.id,
+      options.user,
+      resident,
+    )?
+    .ok_or(Error::UnsupportedAlgorithm)?;
+
+    let client_data = client_data("webauthn.create", options.challenge.into(), origin)?;
+
+    let mut auth_data = AuthenticatorData::new(
+      &credential.rp_id,
+      AuthenticatorData::USER_PRESENT | user_verified_flag(user_verified),
+      credential.sign_count,
+    );
+    auth_data.attested_credential_data = Some(AttestedCredentialData {
+      aaguid: self.options.lock().unwrap().aaguid,
+      credential_id: credential.id.clone(),
+      credential_public_key: serde_cbor_2::to_vec(&credential.cose_key()?)?,
+    });
+
+    let att_stmt = match options.attestation {
+      Some(AttestationConveyancePreference::Direct | AttestationConveyancePreference::Indirect) => {
+        // packed self attestation, signed with the credential private key
+        let mut signed = auth_data.to_bytes()?;
+        signed.extend(sha256(&client_data));
+        BTreeMap::from([
+          (
+            Value::Text("alg".into()),
+            Value::Integer(credential.alg().into()),
+          ),
+          (
+            Value::Text("sig".into()),
+            Value::Bytes(credential.sign(&signed)?),
+          ),
+        ])
+      }
+      _ => BTreeMap::new(),
+    };
+    let fmt = if att_stmt.is_empty() {
+      "none"
+    } else {
+      "packed"
+    };
+    let attestation_object = auth_data.attestation_object(fmt, &Value::Map(att_stmt))?;
+
+    let extensions = RegistrationExtensionsClientOutputs {
+      cred_props: options
+        .extensions
+        .and_then(|e| e.cred_props)
+        .filter(|requested| *requested)
+        .map(|_| CredProps { rk: Some(resident) }),
+      ..Default::default()
+    };
+
+    let raw_id = credential.id.clone();
+    credentials.push(credential);
+
+    Ok(RegisterPublicKeyCredential {
+      id: BASE64_URL_SAFE_NO_PAD.encode(&raw_id),
+      raw_id: raw_id.into(),
+      response: webauthn_rs_proto::AuthenticatorAttestationResponseRaw {
+        attestation_object: attestation_object.into(),
+        client_data_json: client_data.into(),
+        transports: Some(vec![AuthenticatorTransport::Internal]),
+      },
+      type_: "public-key".to_string(),
+      extensions,
+    })
+  }
+
+  /// Authenticate using a credential of the virtual authenticator.
+  /// If multiple discoverable credentials match, the most recently created one is used.
+  async fn authenticate(
+    &self,
+    origin: Url,
+    options: PublicKeyCredentialRequestOptions,
+    _timeout: u32,
+    _events: EventSink,
+  ) -> crate::Result<PublicKeyCredential> {
+    #[cfg(feature = "log")]
+    log::debug!("Authenticating with options: {options:?}");
+    if !self.enabled {
+      return Err(Error::NoToken);
+    }
+    let mut credentials = self.credentials.lock().unwrap();
+
+    let credential = credentials
+      .iter_mut()
+      .rev()
+      .filter(|c| c.rp_id == options.rp_id)
+      .find(|c| {
+        if options.allow_credentials.is_empty() {
+          c.resident
+        } else {
+          options
+            .allow_credentials
+            .iter()
+            .any(|a| a.id.as_slice() == c.id)
+        }
+      })
+      .ok_or(Error::NoCredential)?;
+
+    let user_verified = self.user_interaction(options.user_verification)?;
+    credential.sign_count += 1;
+
+    let client_data = client_data("webauthn.get", options.challenge.into(), origin)?;
+    let auth_data = AuthenticatorData::new(
+      &credential.rp_id,
+      AuthenticatorData::USER_PRESENT | user_verified_flag(user_verified),
+      credential.sign_count,
+    )
+    .to_bytes()?;
+
+    let mut signed = auth_data.clone();
+    signed.extend(sha256(&client_data));
+    let signature = credential.sign(&signed)?;
+
+    Ok(PublicKeyCredential {
+      id: BASE64_URL_SAFE_NO_PAD.encode(&credential.id),
+      raw_id: credential.id.clone().into(),
+      type_: "public-key".to_string(),
+      response: webauthn_rs_proto::AuthenticatorAssertionResponseRaw {
+        authenticator_data: auth_data.into(),
+        client_data_json: client_data.into(),
+        signature: signature.into(),
+        user_handle: credential.resident.then(|| credential.user.id.clone()),
+      },
+      extensions: Default::default(),
+    })
+  }
+}
+
+fn client_data(type_: &str, challenge: Vec<u8>, origin: Url) -> crate::Result<Vec<u8>> {
+  Ok(serde_json::to_vec(&CollectedClientData {
+    type_: type_.to_string(),
+    challenge: challenge.into(),
+    origin,
+    cross_origin: None,
+    token_binding: None,
+    unknown_keys: Default::default(),
+  })?)
+}
+
+fn user_verified_flag(user_verified: bool) -> u8 {
+  if user_verified {
+    AuthenticatorData::USER_VERIFIED
+  } else {
+    0
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use openssl::{
+    bn::{BigNum, BigNumContext},
+    ec::{EcGroup, EcKey, EcPoint},
+    hash::MessageDigest,
+    nid::Nid,
+    pkey::{Id, PKey, Public},
+    sign::Verifier,
+  };
+  use serde_json::json;
+  use tauri::Wry;
+
+  use super::*;
+  use crate::extensions::{ExtensionInputs, WithExtensions};
+  use credential::{EDDSA, ES256};
+
+  fn authenticator() -> Webauthn<Wry> {
+    Webauthn {
+      enabled: true,
+      options: Mutex::new(VirtualAuthenticatorOptions::default()),
+      credentials: Mutex::new(Vec::new()),
+      phantom: PhantomData,
+    }
+  }
+
+  fn origin() -> Url {
+    Url::parse("https://example.com").unwrap()
+  }
+
+  fn field<'a>(map: &'a Value, key: &str) -> &'a Value {
+    let Value::Map(map) = map else {
+      panic!("expected a map");
+    };
+    &map[&Value::Text(key.into())]
+  }
+
+  fn bytes(value: &Value) -> &[u8] {
+    match value {
+      Value::Bytes(bytes) => bytes,
+      _ => panic!("expected bytes"),
+    }
+  }
+
+  /// Decodes the COSE_Key of an ES256 or EdDSA credential.
+  fn public_key(cose_key: &[u8]) -> PKey<Public> {
+    let key: Value = serde_cbor_2::from_slice(cose_key).unwrap();
+    let Value::Map(key) = key else {
+      panic!("expected a map");
+    };
+    let x = bytes(&key[&Value::Integer(-2)]);
+    match key[&Value::Integer(3)] {
+      Value::Integer(alg) if alg == ES256.into() => {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let y = bytes(&key[&Value::Integer(-3)]);
+        let mut point = EcPoint::new(&group).unwrap();
+        point
+          .set_affine_coordinates_gfp(
+            &group,
+            &BigNum::from_slice(x).unwrap(),
+            &BigNum::from_slice(y).unwrap(),
+            &mut BigNumContext::new().unwrap(),
+          )
+          .unwrap();
+        PKey::from_ec_key(EcKey::from_public_key(&group, &point).unwrap()).unwrap()
+      }
+      Value::Integer(alg) if alg == EDDSA.into() => {
+        PKey::public_key_from_raw_bytes(x, Id::ED25519).unwrap()
+      }
+      _ => panic!("unexpected algorithm"),
+    }
+  }
+
+  fn verify(key: &PKey<Public>, alg: i64, data: &[u8], signature: &[u8]) -> bool {
+    if alg == ES256 {
+      let mut verifier = Verifier::new(MessageDigest::sha256(), key).unwrap();
+      verifier.update(data).unwrap();
+      verifier.verify(signature).unwrap()
+    } else {
+      let mut verifier = Verifier::new_without_digest(key).unwrap();
+      verifier.verify_oneshot(signature, data).unwrap()
+    }
+  }
+
+  fn round_trip(alg: i64) {
+    let webauthn = authenticator();
+    let options = serde_json::from_value(json!({
+      "rp": { "id": "example.com", "name": "Example" },
+      "user": { "id": "dXNlcg", "name": "user", "displayName": "User" },
+      "challenge": "cmVnaXN0ZXI",
+      "pubKeyCredParams": [{ "type": "public-key", "alg": alg }],
+      "attestation": "direct",
+    }))
+    .unwrap();
+    let credential = tauri::async_runtime::block_on(webauthn.register(
+      origin(),
+      options,
+      60_000,
+      EventSink::Broadcast,
+    ))
+    .unwrap();
+
+    // packed self attestation over the authenticator data and the client data hash
+    let client_data = credential.response.client_data_json.as_slice();
+    let attestation: Value =
+      serde_cbor_2::from_slice(credential.response.attestation_object.as_slice()).unwrap();
+    assert_eq!(field(&attestation, "fmt"), &Value::Text("packed".into()));
+    let auth_data_bytes = bytes(field(&attestation, "authData"));
+    let auth_data = AuthenticatorData::from_bytes(auth_data_bytes).unwrap();
+    assert_eq!(auth_data.rp_id_hash, sha256(b"example.com"));
+    let attested = auth_data.attested_credential_data.unwrap();
+    assert_eq!(attested.credential_id, credential.raw_id.as_slice());
+
+    let att_stmt = field(&attestation, "attStmt");
+    assert_eq!(field(att_stmt, "alg"), &Value::Integer(alg.into()));
+    let key = public_key(&attested.credential_public_key);
+    let mut signed = auth_data_bytes.to_vec();
+    signed.extend(sha256(client_data));
+    assert!(verify(&key, alg, &signed, bytes(field(att_stmt, "sig"))));
+
+    let options = serde_json::from_value(json!({
+      "challenge": "YXV0aGVudGljYXRl",
+      "rpId": "example.com",
+      "allowCredentials": [{ "type": "public-key", "id": credential.id }],
+      "userVerification": "preferred",
+    }))
+    .unwrap();
+    let assertion = tauri::async_runtime::block_on(webauthn.authenticate(
+      origin(),
+      options,
+      60_000,
+      EventSink::Broadcast,
+    ))
+    .unwrap();
+    assert_eq!(assertion.raw_id, credential.raw_id);
+
+    let auth_data_bytes = assertion.response.authenticator_data.as_slice();
+    let auth_data = AuthenticatorData::from_bytes(auth_data_bytes).unwrap();
+    assert_eq!(
+      auth_data.flags,
+      AuthenticatorData::USER_PRESENT | AuthenticatorData::USER_VERIFIED
+    );
+    assert_eq!(auth_data.sign_count, 1);
+    let mut signed = auth_data_bytes.to_vec();
+    signed.extend(sha256(assertion.response.client_data_json.as_slice()));
+    assert!(verify(
+      &key,
+      alg,
+      &signed,
+      assertion.response.signature.as_slice()
+    ));
+  }
+
+  #[test]
+  fn es256_round_trip() {
+    round_trip(ES256);
+  }
+
+  #[test]
+  fn eddsa_round_trip() {
+    round_trip(EDDSA);
+  }
+
+  #[test]
+  fn rejects_large_blob() {
+    let webauthn = authenticator();
+    let options: WithExtensions<PublicKeyCredentialCreationOptions, ExtensionInputs> =
+      serde_json::from_value(json!({
+        "rp": { "id": "example.com", "name": "Example" },
+        "user": { "id": "dXNlcg", "name": "user", "displayName": "User" },
+        "challenge": "cmVnaXN0ZXI",
+        "pubKeyCredParams": [{ "type": "public-key", "alg": ES256 }],
+        "extensions": { "largeBlob": { "read": true } },
+      }))
+      .unwrap();
+    let result = tauri::async_runtime::block_on(webauthn.register_with_extensions(
+      origin(),
+      options.inner,
+      options.extensions,
+      60_000,
+      EventSink::Broadcast,
+    ));
+    assert!(matches!(
+      result,
+      Err(crate::Error::Unsupported("largeBlob"))
+    ));
+  }
+}
@@ -1,7 +1,6 @@
 use std::{fmt::Debug, marker::PhantomData};
 
-use serde::de::DeserializeOwned;
-use tauri::{plugin::PluginApi, AppHandle, Runtime, Url};
+use tauri::{async_runtime::spawn_blocking, plugin::PluginApi, AppHandle, Runtime, Url};
 use webauthn_authenticator_rs::{win10::Win10, AuthenticatorBackend};
 use webauthn_rs_proto::{
   PublicKeyCredential, PublicKeyCredentialCreationOptions, PublicKeyCredentialRequestOptions,
@@ -9,6 +8,7 @@ use webauthn_rs_proto::{
 };
 
 use super::Authenticator;
+use crate::{events::EventSink, Config};
 
 /// Access to the webauthn APIs.
 #[derive(Debug)]
@@ -17,41 +17,55 @@ pub struct Webauthn<R: Runtime> {
 }
 
 impl<R: Runtime> Authenticator<R> for Webauthn<R> {
-  fn init<C: DeserializeOwned>(_app: &AppHandle<R>, _api: PluginApi<R, C>) -> crate::Result<Self> {
+  fn init(
+    _app: &AppHandle<R>,
+    _api: PluginApi<R, Option<Config>>,
+    _config: &Config,
+  ) -> crate::Result<Self> {
     Ok(Webauthn {
       phantom: PhantomData,
     })
   }
 
   /// Register a new credential using native Windows API.
-  fn register(
+  /// The blocking Windows API is called on a separate thread.
+  async fn register(
     &self,
     origin: Url,
     options: PublicKeyCredentialCreationOptions,
     timeout: u32,
+    _events: EventSink,
   ) -> crate::Result<RegisterPublicKeyCredential> {
-    let mut auth = Win10::default();
-    auth
-      .perform_register(origin, options, timeout)
-      .map_err(|e| {
-        #[cfg(feature = "log")]
-        log::error!("Failed to register: {:?}", e);
-        crate::Error::WebAuthn(e)
-      })
+    spawn_blocking(move || {
+      let mut auth = Win10::default();
+      auth
+        .perform_register(origin, options, timeout)
+        .map_err(|e| {
+          #[cfg(feature = "log")]
+          log::error!("Failed to register: {:?}", e);
+          crate::Error::WebAuthn(e)
+        })
+    })
+    .await?
   }
 
   /// Authenticate using native Windows API.
-  fn authenticate(
+  /// The blocking Windows API is called on a separate thread.
+  async fn authenticate(
     &self,
     origin: Url,
     options: PublicKeyCredentialRequestOptions,
     timeout: u32,
+    _events: EventSink,
   ) -> crate::Result<PublicKeyCredential> {
-    let mut auth = Win10::default();
-    auth.perform_auth(origin, options, timeout).map_err(|e| {
-      #[cfg(feature = "log")]
-      log::error!("Failed to authenticate: {:?}", e);
-      crate::Error::WebAuthn(e)
+    spawn_blocking(move || {
+      let mut auth = Win10::default();
+      auth.perform_auth(origin, options, timeout).map_err(|e| {
+        #[cfg(feature = "log")]
+        log::error!("Failed to authenticate: {:?}", e);
+        crate::Error::WebAuthn(e)
+      })
     })
+    .await?
   }
 }
@@ -1,60 +1,386 @@
-use tauri::Url;
-use tauri::{command, AppHandle, Runtime};
-use tokio::task::block_in_place;
+use std::future::Future;
+
+use base64urlsafedata::Base64UrlSafeData;
+use tauri::{command, ipc::JavaScriptChannelId, Runtime, State, Url, Webview};
 use webauthn_rs_proto::{
-  PublicKeyCredential, PublicKeyCredentialCreationOptions, PublicKeyCredentialRequestOptions,
-  RegisterPublicKeyCredential,
+  PublicKeyCredential, PublicKeyCredentialRequestOptions, RegisterPublicKeyCredential,
 };
 
 use crate::authenticators::Authenticator;
+use crate::events::EventSink;
+use crate::extensions::{CreationOptions, ExtensionInputs, ExtensionOutputs, WithExtensions};
+use crate::operation::{OperationKind, OperationState, Operations};
+use crate::Config;
 use crate::Result;
 use crate::WebauthnExt;
-
-const DEFAULT_TIMEOUT: u32 = 60_000;
+use crate::{
+  AuthenticatorSettings, ConfigChange, CredentialList, CredentialUser, Enrollment,
+  FingerprintSensorInfo, PinInfo,
+};
 
 #[command]
 pub(crate) async fn register<R: Runtime>(
-  app: AppHandle<R>,
-  origin: Url,
-  options: PublicKeyCredentialCreationOptions,
+  webview: Webview<R>,
+  config: State<'_, Config>,
+  operations: State<'_, Operations>,
+  origin: Option<Url>,
+  options: CreationOptions,
   timeout: Option<u32>,
-) -> Result<RegisterPublicKeyCredential> {
-  block_in_place(|| {
-    app
+  on_event: JavaScriptChannelId,
+) -> Result<WithExtensions<RegisterPublicKeyCredential, ExtensionOutputs>> {
+  async {
+    let CreationOptions {
+      options,
+      enterprise_attestation,
+    } = options;
+    let origin = config.check_request(&webview, origin, &options.inner.rp.id)?;
+    if enterprise_attestation {
+      config.check_enterprise_attestation(&options.inner.rp.id)?;
+    }
+    let _operation = operations
+      .begin(OperationKind::Register, webview.label(), || {
+        webview.webauthn().cancel()
+      })
+      .await?;
+    webview
       .webauthn()
-      .register(origin, options, timeout.unwrap_or(DEFAULT_TIMEOUT))
-      .log()
-  })
+      .register_with_extensions(
+        origin,
+        options.inner,
+        options.extensions,
+        config.timeout(timeout),
+        event_sink(&webview, on_event),
+      )
+      .await
+  }
+  .await
+  .log()
 }
 
 #[command]
 pub(crate) async fn authenticate<R: Runtime>(
-  app: AppHandle<R>,
-  origin: Url,
-  options: PublicKeyCredentialRequestOptions,
+  webview: Webview<R>,
+  config: State<'_, Config>,
+  operations: State<'_, Operations>,
+  origin: Option<Url>,
+  options: WithExtensions<PublicKeyCredentialRequestOptions, ExtensionInputs>,
+  timeout: Option<u32>,
+  on_event: JavaScriptChannelId,
+) -> Result<WithExtensions<PublicKeyCredential, ExtensionOutputs>> {
+  async {
+    let origin = config.check_request(&webview, origin, &options.inner.rp_id)?;
+    let _operation = operations
+      .begin(OperationKind::Authenticate, webview.label(), || {
+        webview.webauthn().cancel()
+      })
+      .await?;
+    webview
+      .webauthn()
+      .authenticate_with_extensions(
+        origin,
+        options.inner,
+        options.extensions,
+        config.timeout(timeout),
+        event_sink(&webview, on_event),
+      )
+      .await
+  }
+  .await
+  .log()
+}
+
+#[command]
+pub(crate) async fn send_pin<R: Runtime>(
+  webview: Webview<R>,
+  operations: State<'_, Operations>,
+  request_id: u64,
+  pin: String,
+) -> Result<()> {
+  operations.check_owner(webview.label())?;
+  webview.webauthn().send_pin(request_id, pin)?;
+  operations.resume();
+  Ok(())
+}
+
+#[command]
+pub(crate) async fn select_key<R: Runtime>(
+  webview: Webview<R>,
+  operations: State<'_, Operations>,
+  request_id: u64,
+  key: usize,
+) -> Result<()> {
+  operations.check_owner(webview.label())?;
+  webview.webauthn().select_key(request_id, key)?;
+  operations.resume();
+  Ok(())
+}
+
+#[command]
+pub(crate) async fn cancel<R: Runtime>(
+  webview: Webview<R>,
+  operations: State<'_, Operations>,
+) -> Result<()> {
+  operations.check_owner(webview.label())?;
+  webview.webauthn().cancel();
+  Ok(())
+}
+
+#[command]
+pub(crate) async fn pin_info<R: Runtime>(
+  webview: Webview<R>,
+  config: State<'_, Config>,
+  operations: State<'_, Operations>,
+  timeout: Option<u32>,
+  on_event: JavaScriptChannelId,
+) -> Result<PinInfo> {
+  manage(&webview, &operations, on_event, |events| {
+    webview.webauthn().pin_info(config.timeout(timeout), events)
+  })
+  .await
+}
+
+#[command]
+pub(crate) async fn set_pin<R: Runtime>(
+  webview: Webview<R>,
+  config: State<'_, Config>,
+  operations: State<'_, Operations>,
+  pin: String,
+  timeout: Option<u32>,
+  on_event: JavaScriptChannelId,
+) -> Result<()> {
+  manage(&webview, &operations, on_event, |events| {
+    webview
+      .webauthn()
+      .set_pin(pin, config.timeout(timeout), events)
+  })
+  .await
+}
+
+#[command]
+pub(crate) async fn change_pin<R: Runtime>(
+  webview: Webview<R>,
+  config: State<'_, Config>,
+  operations: State<'_, Operations>,
+  current_pin: String,
+  new_pin: String,
+  timeout: Option<u32>,
+  on_event: JavaScriptChannelId,
+) -> Result<()> {
+  manage(&webview, &operations, on_event, |events| {
+    webview
+      .webauthn()
+      .change_pin(current_pin, new_pin, config.timeout(timeout), events)
+  })
+  .await
+}
+
+#[command]
+pub(crate) async fn list_credentials<R: Runtime>(
+  webview: Webview<R>,
+  config: State<'_, Config>,
+  operations: State<'_, Operations>,
+  timeout: Option<u32>,
+  on_event: JavaScriptChannelId,
+) -> Result<CredentialList> {
+  manage(&webview, &operations, on_event, |events| {
+    webview
+      .webauthn()
+      .list_credentials(config.timeout(timeout), events)
+  })
+  .await
+}
+
+#[command]
+pub(crate) async fn delete_credential<R: Runtime>(
+  webview: Webview<R>,
+  config: State<'_, Config>,
+  operations: State<'_, Operations>,
+  credential_id: Base64UrlSafeData,
+  timeout: Option<u32>,
+  on_event: JavaScriptChannelId,
+) -> Result<()> {
+  manage(&webview, &operations, on_event, |events| {
+    webview
+      .webauthn()
+      .delete_credential(credential_id.into(), config.timeout(timeout), events)
+  })
+  .await
+}
+
+#[command]
+pub(crate) async fn update_credential_user<R: Runtime>(
+  webview: Webview<R>,
+  config: State<'_, Config>,
+  operations: State<'_, Operations>,
+  credential_id: Base64UrlSafeData,
+  user: CredentialUser,
+  timeout: Option<u32>,
+  on_event: JavaScriptChannelId,
+) -> Result<()> {
+  manage(&webview, &operations, on_event, |events| {
+    webview.webauthn().update_credential_user(
+      credential_id.into(),
+      user,
+      config.timeout(timeout),
+      events,
+    )
+  })
+  .await
+}
+
+#[command]
+pub(crate) async fn fingerprint_sensor_info<R: Runtime>(
+  webview: Webview<R>,
+  config: State<'_, Config>,
+  operations: State<'_, Operations>,
+  timeout: Option<u32>,
+  on_event: JavaScriptChannelId,
+) -> Result<FingerprintSensorInfo> {
+  manage(&webview, &operations, on_event, |events| {
+    webview
+      .webauthn()
+      .fingerprint_sensor_info(config.timeout(timeout), events)
+  })
+  .await
+}
+
+#[command]
+pub(crate) async fn list_enrollments<R: Runtime>(
+  webview: Webview<R>,
+  config: State<'_, Config>,
+  operations: State<'_, Operations>,
+  timeout: Option<u32>,
+  on_event: JavaScriptChannelId,
+) -> Result<Vec<Enrollment>> {
+  manage(&webview, &operations, on_event, |events| {
+    webview
+      .webauthn()
+      .list_enrollments(config.timeout(timeout), events)
+  })
+  .await
+}
+
+#[command]
+pub(crate) async fn enroll_fingerprint<R: Runtime>(
+  webview: Webview<R>,
+  config: State<'_, Config>,
+  operations: State<'_, Operations>,
+  name: Option<String>,
+  timeout: Option<u32>,
+  on_event: JavaScriptChannelId,
+) -> Result<()> {
+  manage(&webview, &operations, on_event, |events| {
+    webview
+      .webauthn()
+      .enroll_fingerprint(name, config.timeout(timeout), events)
+  })
+  .await
+}
+
+#[command]
+pub(crate) async fn rename_enrollment<R: Runtime>(
+  webview: Webview<R>,
+  config: State<'_, Config>,
+  operations: State<'_, Operations>,
+  id: Base64UrlSafeData,
+  name: String,
   timeout: Option<u32>,
-) -> Result<PublicKeyCredential> {
-  block_in_place(|| {
-    app
+  on_event: JavaScriptChannelId,
+) -> Result<()> {
+  manage(&webview, &operations, on_event, |events| {
+    webview
       .webauthn()
-      .authenticate(origin, options, timeout.unwrap_or(DEFAULT_TIMEOUT))
-      .log()
+      .rename_enrollment(id.into(), name, config.timeout(timeout), events)
   })
+  .await
 }
 
 #[command]
-pub(crate) async fn send_pin<R: Runtime>(app: AppHandle<R>, pin: String) {
-  app.webauthn().send_pin(pin);
+pub(crate) async fn delete_enrollment<R: Runtime>(
+  webview: Webview<R>,
+  config: State<'_, Config>,
+  operations: State<'_, Operations>,
+  id: Base64UrlSafeData,
+  timeout: Option<u32>,
+  on_event: JavaScriptChannelId,
+) -> Result<()> {
+  manage(&webview, &operations, on_event, |events| {
+    webview
+      .webauthn()
+      .delete_enrollment(id.into(), config.timeout(timeout), events)
+  })
+  .await
 }
 
 #[command]
-pub(crate) async fn select_key<R: Runtime>(app: AppHandle<R>, key: usize) {
-  app.webauthn().select_key(key);
+pub(crate) async fn configure_authenticator<R: Runtime>(
+  webview: Webview<R>,
+  config: State<'_, Config>,
+  operations: State<'_, Operations>,
+  change: ConfigChange,
+  timeout: Option<u32>,
+  on_event: JavaScriptChannelId,
+) -> Result<AuthenticatorSettings> {
+  manage(&webview, &operations, on_event, |events| {
+    webview
+      .webauthn()
+      .configure_authenticator(change, config.timeout(timeout), events)
+  })
+  .await
 }
 
 #[command]
-pub(crate) async fn cancel<R: Runtime>(app: AppHandle<R>) {
-  app.webauthn().cancel();
+pub(crate) async fn prepare_reset<R: Runtime>(webview: Webview<R>) -> Result<String> {
+  webview.webauthn().prepare_reset(webview.label()).log()
+}
+
+#[command]
+pub(crate) async fn reset_authenticator<R: Runtime>(
+  webview: Webview<R>,
+  config: State<'_, Config>,
+  operations: State<'_, Operations>,
+  confirmation: String,
+  timeout: Option<u32>,
+  on_event: JavaScriptChannelId,
+) -> Result<()> {
+  manage(&webview, &operations, on_event, |events| {
+    webview.webauthn().reset(
+      confirmation,
+      webview.label().to_string(),
+      config.timeout(timeout),
+      events,
+    )
+  })
+  .await
+}
+
+#[command]
+pub(crate) async fn status(operations: State<'_, Operations>) -> Result<OperationState> {
+  Ok(operations.state())
+}
+
+/// Runs a security key management operation for the calling webview, `operation` gets the sink for `channel`.
+async fn manage<R: Runtime, T, F: Future<Output = Result<T>>>(
+  webview: &Webview<R>,
+  operations: &Operations,
+  channel: JavaScriptChannelId,
+  operation: impl FnOnce(EventSink) -> F,
+) -> Result<T> {
+  async {
+    let _operation = operations
+      .begin(OperationKind::Manage, webview.label(), || {
+        webview.webauthn().cancel()
+      })
+      .await?;
+    operation(event_sink(webview, channel)).await
+  }
+  .await
+  .log()
+}
+
+/// Events go to the channel passed by the calling webview, which is the only receiver of the events of its ceremony.
+fn event_sink<R: Runtime>(webview: &Webview<R>, channel: JavaScriptChannelId) -> EventSink {
+  EventSink::Channel(channel.channel_on(webview.clone()))
 }
 
 trait ResultExt<T> {
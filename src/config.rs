@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use tauri::{Runtime, Url, Webview};
+use webauthn_rs_proto::AuthenticatorTransport;
+
+#[cfg(feature = "virtual-authenticator")]
+use crate::VirtualAuthenticatorOptions;
+use crate::{operation::ConcurrencyPolicy, rp_id, Error, Result};
+
+const DEFAULT_TIMEOUT: u32 = 60_000;
+
+/// Plugin configuration, read from `plugins.webauthn` in `tauri.conf.json`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Config {
+  /// How the origin of a request is determined.
+  pub origin: OriginConfig,
+  /// Timeout in milliseconds used if the frontend does not pass one.
+  pub default_timeout: u32,
+  /// Upper limit in milliseconds for timeouts passed by the frontend.
+  pub max_timeout: Option<u32>,
+  /// Origins that may make requests. If empty, all origins are allowed.
+  pub allowed_origins: Vec<Url>,
+  /// Relying party ids that may be requested. If empty, all relying party ids are allowed.
+  pub allowed_rp_ids: Vec<String>,
+  /// Transports the authenticator may use. If `None`, all transports supported by the backend are used.
+  pub transports: Option<Vec<AuthenticatorTransport>>,
+  /// Whether security keys which only support CTAP1/U2F may be used.
+  /// Only applies to the CTAP2 backend.
+  pub ctap1_fallback: bool,
+  /// Relying party ids which may request `enterprise` attestation, other relying party ids are refused.
+  /// No backend can return enterprise attestation yet, so permitted requests are rejected as unsupported.
+  pub enterprise_attestation_rp_ids: Vec<String>,
+  /// The authenticator backend to use.
+  pub backend: Backend,
+  /// Initial options of the virtual authenticator, they can be replaced at runtime with `set_options`.
+  #[cfg(feature = "virtual-authenticator")]
+  pub virtual_authenticator: VirtualAuthenticatorOptions,
+  /// What happens if a ceremony is started while another one is in progress.
+  pub concurrency: ConcurrencyPolicy,
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Config {
+      origin: OriginConfig::default(),
+      default_timeout: DEFAULT_TIMEOUT,
+      max_timeout: None,
+      allowed_origins: Vec::new(),
+      allowed_rp_ids: Vec::new(),
+      transports: None,
+      ctap1_fallback: true,
+      enterprise_attestation_rp_ids: Vec::new(),
+      backend: Backend::default(),
+      #[cfg(feature = "virtual-authenticator")]
+      virtual_authenticator: VirtualAuthenticatorOptions::default(),
+      concurrency: ConcurrencyPolicy::default(),
+    }
+  }
+}
+
+impl Config {
+  /// Determines the origin of a request made by `webview` and checks that it may be used together with `rp_id`.
+  pub fn check_request<R: Runtime>(
+    &self,
+    webview: &Webview<R>,
+    origin: Option<Url>,
+    rp_id: &str,
+  ) -> Result<Url> {
+    let origin = self.origin.resolve(webview, origin)?;
+    if !self.allowed_origins.is_empty()
+      && !self
+        .allowed_origins
+        .iter()
+        .any(|allowed| allowed.origin() == origin.origin())
+    {
+      return Err(Error::OriginNotAllowed {
+        origin: origin.to_string(),
+        label: webview.label().to_string(),
+      });
+    }
+    if !self.allowed_rp_ids.is_empty() && !self.allowed_rp_ids.iter().any(|id| id == rp_id) {
+      return Err(Error::RpIdNotAllowed(rp_id.to_string()));
+    }
+    rp_id::validate(&origin, rp_id)?;
+    Ok(origin)
+  }
+
+  /// Checks a request for `enterprise` attestation by `rp_id`, which fails for every backend for now.
+  /// The CTAP2 backend can't send the `enterpriseAttestation` parameter, the other backends have no enterprise attestation.
+  pub fn check_enterprise_attestation(&self, rp_id: &str) -> Result<()> {
+    if !self
+      .enterprise_attestation_rp_ids
+      .iter()
+      .any(|id| id == rp_id)
+    {
+      return Err(Error::EnterpriseAttestationNotAllowed(rp_id.to_string()));
+    }
+    Err(Error::Unsupported("enterprise attestation"))
+  }
+
+  /// Returns the timeout to use for a request, limited by `max_timeout`.
+  pub fn timeout(&self, requested: Option<u32>) -> u32 {
+    let timeout = requested.unwrap_or(self.default_timeout);
+    match self.max_timeout {
+      Some(max) => timeout.min(max),
+      None => timeout,
+    }
+  }
+
+  /// Whether the authenticator may use `transport`.
+  pub fn transport_enabled(&self, transport: &AuthenticatorTransport) -> bool {
+    self
+      .transports
+      .as_ref()
+      .map_or(true, |transports| transports.contains(transport))
+  }
+}
+
+/// The authenticator backend handling the requests.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Backend {
+  /// The native authenticator of the platform (CTAP2 on Linux and macOS, the Windows WebAuthn API on Windows and the credential manager on mobile).
+  #[default]
+  Platform,
+  /// The in-memory authenticator, requires the `virtual-authenticator` feature and has to be selected explicitly.
+  Virtual,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OriginConfig {
+  /// Derive the origin from the URL of the calling webview instead of trusting the `origin` argument.
+  #[serde(default)]
+  pub from_webview: bool,
+  /// Maps the origin of a webview to the origin used for WebAuthn,
+  /// e.g. `tauri://localhost` to `https://app.example.com`.
+  #[serde(default)]
+  pub mapping: HashMap<String, Url>,
+  /// Origins each webview (by label) is allowed to use.
+  /// If this is not empty, webviews without an entry are refused.
+  #[serde(default)]
+  pub allowed: HashMap<String, Vec<Url>>,
+}
+
+impl OriginConfig {
+  /// Determines the origin of a request made by `webview`.
+  /// `origin` is the origin passed by the frontend, which is only used if `from_webview` is disabled.
+  pub fn resolve<R: Runtime>(&self, webview: &Webview<R>, origin: Option<Url>) -> Result<Url> {
+    if !self.from_webview {
+      return origin.ok_or(Error::MissingOrigin);
+    }
+    self.resolve_webview_url(webview.label(), &webview.url()?)
+  }
+
+  /// Maps the URL of the webview `label` to the origin used for WebAuthn.
+  fn resolve_webview_url(&self, label: &str, url: &Url) -> Result<Url> {
+    let webview_origin = origin_key(url);
+    let not_allowed = |origin: String| Error::OriginNotAllowed {
+      origin,
+      label: label.to_string(),
+    };
+
+    let mapped = self
+      .mapping
+      .iter()
+      .find(|(key, _)| Url::parse(key).is_ok_and(|key| origin_key(&key) == webview_origin));
+    let origin = match mapped {
+      Some((_, origin)) => origin.clone(),
+      None => Url::parse(&webview_origin).map_err(|_| not_allowed(webview_origin.clone()))?,
+    };
+
+    if !self.allowed.is_empty() {
+      let allowed = self
+        .allowed
+        .get(label)
+        .is_some_and(|allowed| allowed.iter().any(|a| origin_key(a) == origin_key(&origin)));
+      if !allowed {
+        return Err(not_allowed(origin.to_string()));
+      }
+    }
+
+    #[cfg(feature = "log")]
+    log::debug!("Using origin {origin} for webview {label}");
+    Ok(origin)
+  }
+}
+
+/// Identifies the origin of `url` as `scheme://host[:port]`, the port is omitted if it is the default of the scheme.
+/// Unlike [`Url::origin`] this also works for custom schemes like `tauri://localhost`, whose origin is opaque.
+fn origin_key(url: &Url) -> String {
+  let host = url.host_str().unwrap_or_default();
+  match url.port() {
+    Some(port) => format!("{}://{host}:{port}", url.scheme()),
+    None => format!("{}://{host}", url.scheme()),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn url(url: &str) -> Url {
+    Url::parse(url).unwrap()
+  }
+
+  fn config() -> OriginConfig {
+    OriginConfig {
+      from_webview: true,
+      mapping: HashMap::from([
+        (
+          "tauri://localhost".to_string(),
+          url("https://app.example.com"),
+        ),
+        (
+          "http://tauri.localhost/".to_string(),
+          url("https://app.example.com"),
+        ),
+      ]),
+      allowed: HashMap::new(),
+    }
+  }
+
+  #[test]
+  fn maps_tauri_scheme() {
+    let origin = config()
+      .resolve_webview_url("main", &url("tauri://localhost/index.html"))
+      .unwrap();
+    assert_eq!(origin, url("https://app.example.com"));
+  }
+
+  #[test]
+  fn maps_windows_localhost() {
+    let origin = config()
+      .resolve_webview_url("main", &url("http://tauri.localhost/settings?tab=keys"))
+      .unwrap();
+    assert_eq!(origin, url("https://app.example.com"));
+  }
+
+  #[test]
+  fn keeps_unmapped_https_origin() {
+    let origin = config()
+      .resolve_webview_url("main", &url("https://login.example.com/path#fragment"))
+      .unwrap();
+    assert_eq!(origin, url("https://login.example.com"));
+
+    // the port is part of the origin
+    let origin = config()
+      .resolve_webview_url("main", &url("https://login.example.com:8443/"))
+      .unwrap();
+    assert_eq!(origin, url("https://login.example.com:8443"));
+  }
+
+  #[test]
+  fn checks_allowed_origins_per_webview() {
+    let mut config = config();
+    config
+      .allowed
+      .insert("main".to_string(), vec![url("https://app.example.com")]);
+
+    assert!(config
+      .resolve_webview_url("main", &url("tauri://localhost"))
+      .is_ok());
+    assert!(matches!(
+      config.resolve_webview_url("main", &url("https://evil.example.com")),
+      Err(Error::OriginNotAllowed { .. })
+    ));
+    assert!(matches!(
+      config.resolve_webview_url("other", &url("tauri://localhost")),
+      Err(Error::OriginNotAllowed { .. })
+    ));
+  }
+
+  #[test]
+  fn refuses_enterprise_attestation() {
+    let config = Config {
+      enterprise_attestation_rp_ids: vec!["corp.example.com".to_string()],
+      ..Config::default()
+    };
+    assert!(matches!(
+      config.check_enterprise_attestation("example.com"),
+      Err(Error::EnterpriseAttestationNotAllowed(_))
+    ));
+    assert!(matches!(
+      config.check_enterprise_attestation("corp.example.com"),
+      Err(Error::Unsupported("enterprise attestation"))
+    ));
+  }
+}
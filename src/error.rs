@@ -1,4 +1,7 @@
-use serde::{ser::Serializer, Serialize};
+use serde::{
+  ser::{SerializeStruct, Serializer},
+  Serialize,
+};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -6,6 +9,8 @@ pub type Result<T> = std::result::Result<T, Error>;
 pub enum Error {
   #[error(transparent)]
   Io(#[from] std::io::Error),
+  #[error(transparent)]
+  Tauri(#[from] tauri::Error),
   #[cfg(mobile)]
   #[error(transparent)]
   PluginInvoke(#[from] tauri::plugin::mobile::PluginInvokeError),
@@ -18,19 +23,281 @@ pub enum Error {
   NoToken,
   #[error("Failed to create authenticator")]
   Authenticator,
+  #[error("The operation was not allowed by the user")]
+  NotAllowed,
+  #[error("The operation was aborted")]
+  Aborted,
+  #[error("Another operation is already in progress")]
+  OperationInProgress,
+  #[error("The operation was started by another webview")]
+  ForeignOperation,
+  #[error("The request {0} is not pending")]
+  RequestNotPending(u64),
+  #[error("No matching credential found")]
+  NoCredential,
+  #[error("None of the requested algorithms are supported")]
+  UnsupportedAlgorithm,
+  #[error("A credential of this authenticator is already registered")]
+  CredentialExcluded,
+  #[error("The authenticator returned an invalid response")]
+  InvalidResponse,
+  #[error("The origin {0} is not a secure context")]
+  InsecureOrigin(tauri::Url),
+  #[error("The relying party id {rp_id} is not valid for the origin {origin}")]
+  InvalidRpId { origin: tauri::Url, rp_id: String },
+  #[error("No origin was provided")]
+  MissingOrigin,
+  #[error("The origin {origin} is not allowed for the webview {label}")]
+  OriginNotAllowed { origin: String, label: String },
+  #[error("The relying party id {0} is not allowed")]
+  RpIdNotAllowed(String),
+  #[error("The relying party id {0} may not request enterprise attestation")]
+  EnterpriseAttestationNotAllowed(String),
+  #[error("The security key only supports CTAP1/U2F which is disabled")]
+  Ctap1NotAllowed,
+  #[error("The virtual authenticator requires the virtual-authenticator feature")]
+  VirtualAuthenticatorUnavailable,
+  #[error("{0} is not supported by this authenticator")]
+  Unsupported(&'static str),
+  #[error("The security key already has a PIN")]
+  PinAlreadySet,
+  #[error("The security key has no PIN")]
+  PinNotSet,
+  #[error("The PIN must have at least {min_length} characters")]
+  PinTooShort { min_length: u64 },
+  #[error("The PIN must not be longer than {max_length} bytes")]
+  PinTooLong { max_length: usize },
+  #[error("Invalid extension input: {0}")]
+  InvalidExtensionInput(&'static str),
+  #[error("The reset was not confirmed with a valid token")]
+  ResetNotConfirmed,
+  #[error("The security key can only be reset shortly after it was plugged in")]
+  ResetWindowExpired,
   #[cfg(not(any(target_os = "android", target_os = "ios", target_os = "windows")))]
   #[error(transparent)]
-  Ctap2(#[from] authenticator::errors::AuthenticatorError),
-  #[cfg(not(any(target_os = "android", target_os = "ios", target_os = "windows")))]
+  Ctap2(authenticator::errors::AuthenticatorError),
+  #[cfg(any(
+    feature = "virtual-authenticator",
+    not(any(target_os = "android", target_os = "ios", target_os = "windows"))
+  ))]
   #[error(transparent)]
   Cbor2(#[from] serde_cbor_2::Error),
+  #[cfg(any(
+    feature = "virtual-authenticator",
+    not(any(target_os = "android", target_os = "ios", target_os = "windows"))
+  ))]
+  #[error(transparent)]
+  OpenSsl(#[from] openssl::error::ErrorStack),
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios", target_os = "windows")))]
+impl From<authenticator::errors::AuthenticatorError> for Error {
+  fn from(error: authenticator::errors::AuthenticatorError) -> Self {
+    match error {
+      authenticator::errors::AuthenticatorError::CredentialExcluded => Error::CredentialExcluded,
+      error => Error::Ctap2(error),
+    }
+  }
+}
+
+/// Name of the `DOMException` a browser would throw for an error.
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ErrorName {
+  NotAllowedError,
+  InvalidStateError,
+  SecurityError,
+  AbortError,
+  NotSupportedError,
+  TimeoutError,
+  ConstraintError,
+  SyntaxError,
+  UnknownError,
+}
+
+impl Error {
+  /// The `DOMException` name matching this error.
+  pub fn name(&self) -> ErrorName {
+    match self {
+      Error::NotAllowed
+      | Error::ForeignOperation
+      | Error::NoCredential
+      | Error::ResetNotConfirmed
+      | Error::ResetWindowExpired => ErrorName::NotAllowedError,
+      Error::CredentialExcluded
+      | Error::OperationInProgress
+      | Error::RequestNotPending(_)
+      | Error::PinAlreadySet
+      | Error::PinNotSet => ErrorName::InvalidStateError,
+      Error::PinTooShort { .. } | Error::PinTooLong { .. } => ErrorName::ConstraintError,
+      Error::InvalidExtensionInput(_) => ErrorName::SyntaxError,
+      Error::Aborted => ErrorName::AbortError,
+      Error::InsecureOrigin(_)
+      | Error::InvalidRpId { .. }
+      | Error::MissingOrigin
+      | Error::OriginNotAllowed { .. }
+      | Error::RpIdNotAllowed(_)
+      | Error::EnterpriseAttestationNotAllowed(_) => ErrorName::SecurityError,
+      Error::NoToken
+      | Error::UnsupportedAlgorithm
+      | Error::Ctap1NotAllowed
+      | Error::VirtualAuthenticatorUnavailable
+      | Error::Unsupported(_) => ErrorName::NotSupportedError,
+      #[cfg(all(desktop, windows))]
+      Error::WebAuthn(error) => webauthn_error_name(error),
+      #[cfg(not(any(target_os = "android", target_os = "ios", target_os = "windows")))]
+      Error::Ctap2(error) => ctap2_error_name(error),
+      _ => ErrorName::UnknownError,
+    }
+  }
+
+  /// A stable, machine-readable code identifying the error.
+  pub fn code(&self) -> &'static str {
+    match self {
+      Error::Io(_) => "io",
+      Error::Tauri(_) => "tauri",
+      #[cfg(mobile)]
+      Error::PluginInvoke(_) => "pluginInvoke",
+      #[cfg(all(desktop, windows))]
+      Error::WebAuthn(_) => "webauthn",
+      Error::SerdeJson(_) => "serdeJson",
+      Error::NoToken => "noToken",
+      Error::Authenticator => "authenticator",
+      Error::NotAllowed => "notAllowed",
+      Error::Aborted => "aborted",
+      Error::OperationInProgress => "operationInProgress",
+      Error::ForeignOperation => "foreignOperation",
+      Error::RequestNotPending(_) => "requestNotPending",
+      Error::NoCredential => "noCredential",
+      Error::UnsupportedAlgorithm => "unsupportedAlgorithm",
+      Error::CredentialExcluded => "credentialExcluded",
+      Error::InvalidResponse => "invalidResponse",
+      Error::InsecureOrigin(_) => "insecureOrigin",
+      Error::InvalidRpId { .. } => "invalidRpId",
+      Error::MissingOrigin => "missingOrigin",
+      Error::OriginNotAllowed { .. } => "originNotAllowed",
+      Error::RpIdNotAllowed(_) => "rpIdNotAllowed",
+      Error::EnterpriseAttestationNotAllowed(_) => "enterpriseAttestationNotAllowed",
+      Error::Ctap1NotAllowed => "ctap1NotAllowed",
+      Error::VirtualAuthenticatorUnavailable => "virtualAuthenticatorUnavailable",
+      Error::Unsupported(_) => "unsupported",
+      Error::PinAlreadySet => "pinAlreadySet",
+      Error::PinNotSet => "pinNotSet",
+      Error::PinTooShort { .. } => "pinTooShort",
+      Error::PinTooLong { .. } => "pinTooLong",
+      Error::InvalidExtensionInput(_) => "invalidExtensionInput",
+      Error::ResetNotConfirmed => "resetNotConfirmed",
+      Error::ResetWindowExpired => "resetWindowExpired",
+      #[cfg(not(any(target_os = "android", target_os = "ios", target_os = "windows")))]
+      Error::Ctap2(_) => "ctap2",
+      #[cfg(any(
+        feature = "virtual-authenticator",
+        not(any(target_os = "android", target_os = "ios", target_os = "windows"))
+      ))]
+      Error::Cbor2(_) => "cbor",
+      #[cfg(any(
+        feature = "virtual-authenticator",
+        not(any(target_os = "android", target_os = "ios", target_os = "windows"))
+      ))]
+      Error::OpenSsl(_) => "openSsl",
+    }
+  }
+
+  /// Backend specific details about the error.
+  fn detail(&self) -> Option<String> {
+    match self {
+      #[cfg(mobile)]
+      Error::PluginInvoke(error) => Some(format!("{error:?}")),
+      #[cfg(all(desktop, windows))]
+      Error::WebAuthn(error) => Some(format!("{error:?}")),
+      #[cfg(not(any(target_os = "android", target_os = "ios", target_os = "windows")))]
+      Error::Ctap2(error) => Some(format!("{error:?}")),
+      _ => None,
+    }
+  }
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios", target_os = "windows")))]
+fn ctap2_error_name(error: &authenticator::errors::AuthenticatorError) -> ErrorName {
+  use authenticator::{
+    ctap2::commands::StatusCode,
+    errors::{
+      AuthenticatorError, CommandError, HIDError, PinError, U2FTokenError, UnsupportedOption,
+    },
+  };
+
+  match error {
+    AuthenticatorError::CredentialExcluded => ErrorName::InvalidStateError,
+    AuthenticatorError::CancelledByUser => ErrorName::AbortError,
+    AuthenticatorError::InvalidRelyingPartyInput => ErrorName::SecurityError,
+    AuthenticatorError::NoConfiguredTransports => ErrorName::NotSupportedError,
+    AuthenticatorError::UnsupportedOption(
+      UnsupportedOption::ResidentKey | UnsupportedOption::UserVerification,
+    ) => ErrorName::ConstraintError,
+    AuthenticatorError::UnsupportedOption(_) => ErrorName::NotSupportedError,
+    AuthenticatorError::PinError(
+      PinError::PinNotSet | PinError::PinIsTooShort | PinError::PinIsTooLong(_),
+    ) => ErrorName::ConstraintError,
+    AuthenticatorError::PinError(_) => ErrorName::NotAllowedError,
+    // the transaction reports a timeout as U2F "not allowed"
+    AuthenticatorError::U2FToken(U2FTokenError::NotAllowed) => ErrorName::TimeoutError,
+    AuthenticatorError::U2FToken(U2FTokenError::InvalidState) => ErrorName::InvalidStateError,
+    AuthenticatorError::U2FToken(U2FTokenError::NotSupported) => ErrorName::NotSupportedError,
+    AuthenticatorError::U2FToken(U2FTokenError::ConstraintError) => ErrorName::ConstraintError,
+    AuthenticatorError::HIDError(HIDError::DeviceNotSupported | HIDError::UnsupportedCommand) => {
+      ErrorName::NotSupportedError
+    }
+    AuthenticatorError::HIDError(HIDError::Command(CommandError::StatusCode(status, _))) => {
+      match status {
+        StatusCode::CredentialExcluded => ErrorName::InvalidStateError,
+        StatusCode::NoCredentials
+        | StatusCode::NotAllowed
+        | StatusCode::OperationDenied
+        | StatusCode::PinInvalid
+        | StatusCode::PinBlocked
+        | StatusCode::PinAuthInvalid
+        | StatusCode::PinAuthBlocked
+        | StatusCode::UvBlocked
+        | StatusCode::UvInvalid => ErrorName::NotAllowedError,
+        StatusCode::UserActionTimeout => ErrorName::TimeoutError,
+        StatusCode::KeepaliveCancel => ErrorName::AbortError,
+        StatusCode::UnsupportedAlgorithm
+        | StatusCode::UnsupportedExtension
+        | StatusCode::UnsupportedOption => ErrorName::NotSupportedError,
+        StatusCode::KeyStoreFull | StatusCode::PinNotSet | StatusCode::PinPolicyViolation => {
+          ErrorName::ConstraintError
+        }
+        _ => ErrorName::UnknownError,
+      }
+    }
+    _ => ErrorName::UnknownError,
+  }
+}
+
+#[cfg(all(desktop, windows))]
+fn webauthn_error_name(error: &webauthn_authenticator_rs::error::WebauthnCError) -> ErrorName {
+  use webauthn_authenticator_rs::error::WebauthnCError;
+
+  match error {
+    WebauthnCError::Cancelled => ErrorName::AbortError,
+    WebauthnCError::Security => ErrorName::SecurityError,
+    WebauthnCError::NotSupported | WebauthnCError::InvalidAlgorithm => ErrorName::NotSupportedError,
+    WebauthnCError::Timeout => ErrorName::TimeoutError,
+    _ => ErrorName::UnknownError,
+  }
 }
 
+/// Errors are serialized as `{ name, code, message, detail }` so the frontend can handle them like a `DOMException`.
 impl Serialize for Error {
   fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
   where
     S: Serializer,
   {
-    serializer.serialize_str(self.to_string().as_ref())
+    let mut state = serializer.serialize_struct("Error", 4)?;
+    state.serialize_field("name", &self.name())?;
+    state.serialize_field("code", self.code())?;
+    state.serialize_field("message", &self.to_string())?;
+    state.serialize_field("detail", &self.detail())?;
+    state.end()
   }
 }
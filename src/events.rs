@@ -0,0 +1,181 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tauri::{
+  ipc::{Channel, InvokeResponseBody},
+  AppHandle, Emitter, Runtime,
+};
+
+use crate::{ErrorName, OperationKind, Result};
+
+/// Name of the event emitted for authenticator status updates.
+pub const EVENT_NAME: &str = "tauri-plugin-webauthn";
+
+/// PIN and user verification status of the authenticator.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum PinEvent {
+  PinRequired,
+  InvalidPin { attempts_remaining: Option<u8> },
+  PinAuthBlocked,
+  PinBlocked,
+  InvalidUv { attempts_remaining: Option<u8> },
+  UvBlocked,
+  PinTooShort,
+  PinTooLong { length: usize },
+  PinNotSet,
+}
+
+/// Feedback for one fingerprint sample during an enrollment.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnrollmentSample {
+  pub status: SampleStatus,
+  /// Number of samples still required to finish the enrollment.
+  pub remaining_samples: u64,
+}
+
+/// Quality of a fingerprint sample as reported by the sensor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SampleStatus {
+  Good,
+  TooHigh,
+  TooLow,
+  TooLeft,
+  TooRight,
+  TooFast,
+  TooSlow,
+  PoorQuality,
+  TooSkewed,
+  TooShort,
+  MergeFailure,
+  /// The fingerprint is already enrolled.
+  Exists,
+  /// The user did not touch the sensor.
+  NoUserActivity,
+  /// The user did not lift the finger off the sensor.
+  NoUserPresenceTransition,
+  Other,
+}
+
+/// A security key that was plugged in or unplugged during a ceremony.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceInfo {
+  /// Platform specific path of the device, e.g. `/dev/hidraw3`.
+  pub path: String,
+  /// The product name reported by the device.
+  pub name: Option<String>,
+}
+
+/// How a ceremony has ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Outcome {
+  Completed,
+  Cancelled,
+  TimedOut,
+  Failed,
+}
+
+impl Outcome {
+  pub(crate) fn of<T>(result: &Result<T>) -> Self {
+    match result {
+      Ok(_) => Outcome::Completed,
+      Err(e) => match e.name() {
+        ErrorName::AbortError => Outcome::Cancelled,
+        ErrorName::TimeoutError => Outcome::TimedOut,
+        _ => Outcome::Failed,
+      },
+    }
+  }
+}
+
+/// A user account of a discoverable credential, offered for selection.
+#[derive(Debug, Clone)]
+pub struct Account {
+  pub id: Vec<u8>,
+  pub name: Option<String>,
+  pub display_name: Option<String>,
+}
+
+/// Handles the user interaction of a ceremony in native code instead of the webview.
+/// The methods are called from a background thread and may block until the user has answered.
+/// Only the CTAP2 backend (Linux and macOS) requires user interaction, the other platforms show their own UI.
+pub trait UserInteractionHandler: Send + Sync {
+  /// The ceremony has started.
+  fn started(&self, kind: OperationKind) {
+    let _ = kind;
+  }
+
+  /// The ceremony has ended, the result is returned by `register` or `authenticate`.
+  fn finished(&self, outcome: Outcome) {
+    let _ = outcome;
+  }
+
+  /// A security key was plugged in during the ceremony.
+  fn device_attached(&self, device: DeviceInfo) {
+    let _ = device;
+  }
+
+  /// A security key was unplugged during the ceremony.
+  fn device_removed(&self, device: DeviceInfo) {
+    let _ = device;
+  }
+
+  /// The user has to select one of multiple authenticators by touching it.
+  fn select_device(&self) {}
+
+  /// The user has to confirm their presence, e.g. by touching the security key.
+  fn presence_required(&self) {}
+
+  /// The authenticator needs the PIN of the user.
+  /// `attempts_remaining` is set if the previous PIN was wrong. Returning `None` cancels the ceremony.
+  fn request_pin(&self, attempts_remaining: Option<u8>) -> Option<String>;
+
+  /// The user has to select one of the accounts stored on the authenticator.
+  /// Returns the index of the selected account, `None` cancels the ceremony.
+  fn select_account(&self, accounts: Vec<Account>) -> Option<usize>;
+
+  /// A PIN or user verification error which does not require an answer, e.g. a blocked PIN.
+  fn pin_event(&self, event: PinEvent) {
+    let _ = event;
+  }
+
+  /// The user has to unplug and re-insert the security key before it can be reset.
+  fn reinsert_key(&self) {}
+
+  /// A fingerprint sample was captured during an enrollment.
+  fn enrollment_sample(&self, sample: EnrollmentSample) {
+    let _ = sample;
+  }
+}
+
+/// Where the status events of a ceremony (PIN requests, key selection, ...) are delivered.
+#[derive(Clone)]
+pub enum EventSink {
+  /// Emit to all listeners of the app, including every window. Only meant for Rust callers.
+  Broadcast,
+  /// Send through an IPC channel, only the owner of the channel receives the events.
+  /// Ceremonies started from the frontend always use a channel.
+  Channel(Channel),
+  /// Let native code answer PIN requests and account selection, no events are emitted.
+  Handler(Arc<dyn UserInteractionHandler>),
+}
+
+impl EventSink {
+  pub(crate) fn send<R: Runtime, S: Serialize>(&self, app: &AppHandle<R>, event: &S) {
+    let result = match self {
+      EventSink::Broadcast => app.emit(EVENT_NAME, event),
+      EventSink::Channel(channel) => serde_json::to_string(event)
+        .map_err(Into::into)
+        .and_then(|json| channel.send(InvokeResponseBody::Json(json))),
+      EventSink::Handler(_) => Ok(()),
+    };
+    if let Err(_e) = result {
+      #[cfg(feature = "log")]
+      log::warn!("Failed to deliver event: {_e}");
+    }
+  }
+}
@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+
+use base64urlsafedata::Base64UrlSafeData;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+use webauthn_rs_proto::PublicKeyCredentialCreationOptions;
+
+/// Client extension inputs which are not part of the webauthn-rs-proto options.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtensionInputs {
+  pub prf: Option<PrfInputs>,
+  /// The `largeBlob` extension is not supported by any backend, passing it rejects the request as unsupported.
+  pub large_blob: Option<serde_json::Value>,
+}
+
+/// Client extension outputs which are not part of the webauthn-rs-proto credentials.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtensionOutputs {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub prf: Option<PrfOutputs>,
+}
+
+/// Inputs of the WebAuthn `prf` extension, evaluated with the CTAP2 `hmac-secret` extension.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrfInputs {
+  pub eval: Option<PrfValues>,
+  /// Inputs for specific credentials, keyed by the base64url encoded credential id.
+  /// Only allowed during authentication and only for credentials in `allowCredentials`.
+  #[serde(default)]
+  pub eval_by_credential: HashMap<String, PrfValues>,
+}
+
+/// Inputs or results of the `prf` extension.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrfValues {
+  pub first: Base64UrlSafeData,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub second: Option<Base64UrlSafeData>,
+}
+
+/// Outputs of the `prf` extension.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PrfOutputs {
+  /// Whether the new credential supports the extension, only set during registration.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub enabled: Option<bool>,
+  /// The evaluated PRF, only set during authentication.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub results: Option<PrfValues>,
+}
+
+/// Ceremony options or a credential together with the extensions webauthn-rs-proto has no fields for.
+/// The extensions are (de)serialized as part of the `extensions` object of `inner`,
+/// so the JSON matches the WebAuthn format.
+#[derive(Debug, Clone)]
+pub struct WithExtensions<T, E> {
+  pub inner: T,
+  pub extensions: E,
+}
+
+impl<T, E: Default> From<T> for WithExtensions<T, E> {
+  fn from(inner: T) -> Self {
+    WithExtensions {
+      inner,
+      extensions: E::default(),
+    }
+  }
+}
+
+impl<'de, T: DeserializeOwned> Deserialize<'de> for WithExtensions<T, ExtensionInputs> {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    use serde::de::Error;
+
+    let value = serde_json::Value::deserialize(deserializer)?;
+    let extensions = match value.get("extensions") {
+      Some(extensions) if !extensions.is_null() => {
+        ExtensionInputs::deserialize(extensions).map_err(D::Error::custom)?
+      }
+      _ => ExtensionInputs::default(),
+    };
+    Ok(WithExtensions {
+      inner: T::deserialize(value).map_err(D::Error::custom)?,
+      extensions,
+    })
+  }
+}
+
+impl<T: Serialize, E: Serialize> Serialize for WithExtensions<T, E> {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    use serde::ser::Error;
+
+    let mut value = serde_json::to_value(&self.inner).map_err(S::Error::custom)?;
+    if let (Some(inner), serde_json::Value::Object(extensions)) = (
+      value.get_mut("extensions").and_then(|e| e.as_object_mut()),
+      serde_json::to_value(&self.extensions).map_err(S::Error::custom)?,
+    ) {
+      inner.extend(extensions);
+    }
+    value.serialize(serializer)
+  }
+}
+
+/// Registration options together with whether they request `enterprise` attestation,
+/// which webauthn-rs-proto can't represent. Such options are deserialized with `direct` attestation.
+#[derive(Debug, Clone)]
+pub(crate) struct CreationOptions {
+  pub options: WithExtensions<PublicKeyCredentialCreationOptions, ExtensionInputs>,
+  pub enterprise_attestation: bool,
+}
+
+impl<'de> Deserialize<'de> for CreationOptions {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    use serde::de::Error;
+
+    let mut value = serde_json::Value::deserialize(deserializer)?;
+    let enterprise_attestation = match value.get_mut("attestation") {
+      Some(attestation) if *attestation == "enterprise" => {
+        *attestation = "direct".into();
+        true
+      }
+      _ => false,
+    };
+    Ok(CreationOptions {
+      options: WithExtensions::deserialize(value).map_err(D::Error::custom)?,
+      enterprise_attestation,
+    })
+  }
+}
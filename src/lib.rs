@@ -1,23 +1,55 @@
-use authenticators::Authenticator;
+use operation::Operations;
 use tauri::{
-  plugin::{Builder, TauriPlugin},
-  Manager, Runtime,
+  plugin::{Builder as PluginBuilder, TauriPlugin},
+  Manager, Runtime, Url,
 };
+use webauthn_rs_proto::AuthenticatorTransport;
 
+#[cfg(any(
+  feature = "virtual-authenticator",
+  not(any(target_os = "android", target_os = "ios", target_os = "windows"))
+))]
+mod auth_data;
 mod authenticators;
 mod commands;
+mod config;
 mod error;
+mod events;
+mod extensions;
+mod management;
+mod operation;
+mod rp_id;
 
-pub use error::{Error, Result};
+#[cfg(any(
+  feature = "virtual-authenticator",
+  not(any(target_os = "android", target_os = "ios", target_os = "windows"))
+))]
+pub use auth_data::{AttestedCredentialData, AuthenticatorData};
+#[cfg(feature = "virtual-authenticator")]
+pub use authenticators::virtual_authenticator::VirtualAuthenticatorOptions;
+pub use authenticators::Authenticator;
+pub use config::{Backend, Config, OriginConfig};
+pub use error::{Error, ErrorName, Result};
+pub use events::{
+  Account, DeviceInfo, EnrollmentSample, EventSink, Outcome, PinEvent, SampleStatus,
+  UserInteractionHandler, EVENT_NAME,
+};
+pub use extensions::{
+  ExtensionInputs, ExtensionOutputs, PrfInputs, PrfOutputs, PrfValues, WithExtensions,
+};
+pub use management::{
+  AuthenticatorSettings, ConfigChange, CredentialList, CredentialUser, Enrollment, FingerprintKind,
+  FingerprintSensorInfo, PinInfo, RelyingPartyCredentials, StoredCredential,
+};
+pub use operation::{ConcurrencyPolicy, OperationKind, OperationState};
 
-#[cfg(not(any(target_os = "android", target_os = "ios", target_os = "windows")))]
-type Webauthn<R> = authenticators::ctap2::Webauthn<R>;
-#[cfg(all(desktop, windows))]
-type Webauthn<R> = authenticators::windows::Webauthn<R>;
-#[cfg(mobile)]
-type Webauthn<R> = authenticators::mobile::Webauthn<R>;
+#[cfg(not(feature = "virtual-authenticator"))]
+type Webauthn<R> = authenticators::Platform<R>;
+#[cfg(feature = "virtual-authenticator")]
+type Webauthn<R> = authenticators::backend::Webauthn<R>;
 
 /// Extensions to [`tauri::App`], [`tauri::AppHandle`] and [`tauri::Window`] to access the webauthn APIs.
+/// The returned authenticator implements [`Authenticator`], whose operations are async and can be awaited on any runtime.
 pub trait WebauthnExt<R: Runtime> {
   fn webauthn(&self) -> &Webauthn<R>;
 }
@@ -28,20 +60,165 @@ impl<R: Runtime, T: Manager<R>> crate::WebauthnExt<R> for T {
   }
 }
 
-/// Initializes the plugin.
-pub fn init<R: Runtime>() -> TauriPlugin<R> {
-  Builder::new("webauthn")
-    .invoke_handler(tauri::generate_handler![
-      commands::register,
-      commands::authenticate,
-      commands::send_pin,
-      commands::select_key,
-      commands::cancel,
-    ])
-    .setup(|app, api| {
-      let webauthn = Webauthn::init(app, api)?;
-      app.manage(webauthn);
-      Ok(())
-    })
-    .build()
+/// Initializes the plugin with the configuration from `tauri.conf.json`.
+pub fn init<R: Runtime>() -> TauriPlugin<R, Option<Config>> {
+  Builder::new().build()
+}
+
+/// Builder for the plugin.
+/// Options set on the builder take precedence over the configuration in `tauri.conf.json`.
+#[derive(Debug, Default)]
+pub struct Builder {
+  default_timeout: Option<u32>,
+  max_timeout: Option<u32>,
+  allowed_origins: Vec<Url>,
+  allowed_rp_ids: Vec<String>,
+  transports: Option<Vec<AuthenticatorTransport>>,
+  ctap1_fallback: Option<bool>,
+  enterprise_attestation_rp_ids: Vec<String>,
+  backend: Option<Backend>,
+  concurrency: Option<ConcurrencyPolicy>,
+  #[cfg(feature = "virtual-authenticator")]
+  virtual_authenticator: Option<VirtualAuthenticatorOptions>,
+}
+
+impl Builder {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Timeout in milliseconds used if the frontend does not pass one.
+  pub fn default_timeout(mut self, timeout: u32) -> Self {
+    self.default_timeout = Some(timeout);
+    self
+  }
+
+  /// Upper limit in milliseconds for timeouts passed by the frontend.
+  pub fn max_timeout(mut self, timeout: u32) -> Self {
+    self.max_timeout = Some(timeout);
+    self
+  }
+
+  /// Allows requests from `origin`. If no origin is allowed, all origins are allowed.
+  pub fn allowed_origin(mut self, origin: Url) -> Self {
+    self.allowed_origins.push(origin);
+    self
+  }
+
+  /// Allows requests for `rp_id`. If no relying party id is allowed, all relying party ids are allowed.
+  pub fn allowed_rp_id(mut self, rp_id: impl Into<String>) -> Self {
+    self.allowed_rp_ids.push(rp_id.into());
+    self
+  }
+
+  /// Restricts the transports the authenticator may use.
+  pub fn transports(
+    mut self,
+    transports: impl IntoIterator<Item = AuthenticatorTransport>,
+  ) -> Self {
+    self.transports = Some(transports.into_iter().collect());
+    self
+  }
+
+  /// Whether security keys which only support CTAP1/U2F may be used.
+  pub fn ctap1_fallback(mut self, enabled: bool) -> Self {
+    self.ctap1_fallback = Some(enabled);
+    self
+  }
+
+  /// Allows `rp_id` to request `enterprise` attestation, which every backend rejects as unsupported for now.
+  pub fn enterprise_attestation_rp_id(mut self, rp_id: impl Into<String>) -> Self {
+    self.enterprise_attestation_rp_ids.push(rp_id.into());
+    self
+  }
+
+  /// The authenticator backend to use.
+  pub fn backend(mut self, backend: Backend) -> Self {
+    self.backend = Some(backend);
+    self
+  }
+
+  /// What happens if a ceremony is started while another one is in progress.
+  pub fn concurrency(mut self, policy: ConcurrencyPolicy) -> Self {
+    self.concurrency = Some(policy);
+    self
+  }
+
+  /// Initial options of the virtual authenticator.
+  #[cfg(feature = "virtual-authenticator")]
+  pub fn virtual_authenticator(mut self, options: VirtualAuthenticatorOptions) -> Self {
+    self.virtual_authenticator = Some(options);
+    self
+  }
+
+  fn apply(self, config: &mut Config) {
+    if let Some(timeout) = self.default_timeout {
+      config.default_timeout = timeout;
+    }
+    if let Some(timeout) = self.max_timeout {
+      config.max_timeout = Some(timeout);
+    }
+    config.allowed_origins.extend(self.allowed_origins);
+    config.allowed_rp_ids.extend(self.allowed_rp_ids);
+    if let Some(transports) = self.transports {
+      config.transports = Some(transports);
+    }
+    if let Some(enabled) = self.ctap1_fallback {
+      config.ctap1_fallback = enabled;
+    }
+    config
+      .enterprise_attestation_rp_ids
+      .extend(self.enterprise_attestation_rp_ids);
+    if let Some(backend) = self.backend {
+      config.backend = backend;
+    }
+    if let Some(policy) = self.concurrency {
+      config.concurrency = policy;
+    }
+    #[cfg(feature = "virtual-authenticator")]
+    if let Some(options) = self.virtual_authenticator {
+      config.virtual_authenticator = options;
+    }
+  }
+
+  pub fn build<R: Runtime>(self) -> TauriPlugin<R, Option<Config>> {
+    PluginBuilder::<R, Option<Config>>::new("webauthn")
+      .invoke_handler(tauri::generate_handler![
+        commands::register,
+        commands::authenticate,
+        commands::send_pin,
+        commands::select_key,
+        commands::cancel,
+        commands::status,
+        commands::pin_info,
+        commands::set_pin,
+        commands::change_pin,
+        commands::list_credentials,
+        commands::delete_credential,
+        commands::update_credential_user,
+        commands::fingerprint_sensor_info,
+        commands::list_enrollments,
+        commands::enroll_fingerprint,
+        commands::rename_enrollment,
+        commands::delete_enrollment,
+        commands::configure_authenticator,
+        commands::prepare_reset,
+        commands::reset_authenticator,
+      ])
+      .setup(|app, api| {
+        let mut config = api.config().clone().unwrap_or_default();
+        self.apply(&mut config);
+        #[cfg(not(feature = "virtual-authenticator"))]
+        if config.backend == Backend::Virtual {
+          return Err(Error::VirtualAuthenticatorUnavailable.into());
+        }
+
+        let webauthn = Webauthn::init(app, api, &config)?;
+        app.manage(webauthn);
+        app.manage(Operations::new(config.concurrency));
+        app.manage(config);
+        Ok(())
+      })
+      .build()
+  }
 }
@@ -0,0 +1,124 @@
+use base64urlsafedata::Base64UrlSafeData;
+use serde::{Deserialize, Serialize};
+use webauthn_rs_proto::CredentialProtectionPolicy;
+
+/// PIN state of a security key, as reported by `authenticatorGetInfo`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PinInfo {
+  /// Whether the security key supports a PIN.
+  pub supported: bool,
+  /// Whether a PIN has been set.
+  pub is_set: bool,
+  /// Minimum number of characters of a new PIN.
+  pub min_length: u64,
+  /// Whether the PIN has to be changed before the security key can be used.
+  pub force_change: bool,
+  /// Whether the security key has built-in user verification, e.g. a fingerprint sensor.
+  pub uv_supported: bool,
+  /// Whether the built-in user verification has been set up.
+  pub uv_configured: bool,
+  /// Remaining PIN attempts before the PIN is blocked.
+  /// Always `None`, the CTAP2 backend can't send getPINRetries yet.
+  pub pin_retries: Option<u8>,
+  /// Remaining attempts of the built-in user verification before it is blocked.
+  /// Always `None`, the CTAP2 backend can't send getUVRetries yet.
+  pub uv_retries: Option<u8>,
+}
+
+/// Discoverable credentials stored on a security key.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialList {
+  /// Number of discoverable credentials stored on the key.
+  pub existing: u64,
+  /// Number of additional discoverable credentials the key can store.
+  pub remaining: u64,
+  pub relying_parties: Vec<RelyingPartyCredentials>,
+}
+
+/// The discoverable credentials of one relying party.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelyingPartyCredentials {
+  pub rp_id: String,
+  pub rp_name: Option<String>,
+  pub credentials: Vec<StoredCredential>,
+}
+
+/// A discoverable credential stored on a security key.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredCredential {
+  pub id: Base64UrlSafeData,
+  pub user: CredentialUser,
+  /// `None` if the key reports an unknown policy.
+  pub cred_protect: Option<CredentialProtectionPolicy>,
+}
+
+/// The user account a discoverable credential belongs to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialUser {
+  pub id: Base64UrlSafeData,
+  pub name: Option<String>,
+  pub display_name: Option<String>,
+}
+
+/// The fingerprint sensor of a security key.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FingerprintSensorInfo {
+  pub kind: FingerprintKind,
+  /// Maximum number of samples needed for an enrollment.
+  pub max_samples: u64,
+  /// Maximum length of an enrollment name in bytes, not reported by keys implementing the CTAP 2.1 preview.
+  pub max_name_length: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FingerprintKind {
+  Touch,
+  Swipe,
+  Other,
+}
+
+/// A fingerprint enrolled on a security key.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Enrollment {
+  pub id: Base64UrlSafeData,
+  pub name: Option<String>,
+}
+
+/// A change of the security key configuration with the CTAP 2.1 `authenticatorConfig` command.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum ConfigChange {
+  /// Allows the key to return enterprise attestation to permitted relying parties.
+  EnableEnterpriseAttestation,
+  /// Enables or disables user verification for every operation.
+  ToggleAlwaysUv,
+  /// Raises the minimum PIN length, it can only be lowered by resetting the key.
+  #[serde(rename_all = "camelCase")]
+  SetMinPinLength {
+    min_pin_length: Option<u64>,
+    /// Relying parties which may read the minimum PIN length with the `minPinLength` extension.
+    #[serde(default)]
+    rp_ids: Vec<String>,
+    /// Require the user to change the PIN before the key can be used again.
+    #[serde(default)]
+    force_change_pin: bool,
+  },
+}
+
+/// The configuration of a security key after an `authenticatorConfig` change.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthenticatorSettings {
+  pub always_uv: bool,
+  pub enterprise_attestation: bool,
+  pub min_pin_length: u64,
+  pub force_pin_change: bool,
+}
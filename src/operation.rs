@@ -0,0 +1,164 @@
+use std::sync::{
+  atomic::{AtomicU64, Ordering},
+  Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+use crate::{Error, Result};
+
+/// What happens if a ceremony is started while another one is in progress.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConcurrencyPolicy {
+  /// Fail the new ceremony with an `InvalidStateError`.
+  #[default]
+  Reject,
+  /// Start the new ceremony once the current one has finished.
+  Queue,
+  /// Cancel the current ceremony and start the new one.
+  Replace,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OperationKind {
+  Register,
+  Authenticate,
+  /// Managing the security key, e.g. changing its PIN.
+  Manage,
+}
+
+/// The state of the authenticator as reported by the `status` command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "camelCase")]
+pub enum OperationState {
+  Idle,
+  InProgress { id: u64, kind: OperationKind },
+  AwaitingPin { id: u64, kind: OperationKind },
+  AwaitingSelection { id: u64, kind: OperationKind },
+}
+
+impl OperationState {
+  fn with(&self, update: fn(u64, OperationKind) -> Self) -> Self {
+    match *self {
+      OperationState::Idle => OperationState::Idle,
+      OperationState::InProgress { id, kind }
+      | OperationState::AwaitingPin { id, kind }
+      | OperationState::AwaitingSelection { id, kind } => update(id, kind),
+    }
+  }
+}
+
+/// Tracks the ceremony currently using the authenticator.
+/// Only one ceremony can run at a time, the [`ConcurrencyPolicy`] decides what happens to additional ones.
+pub struct Operations {
+  policy: ConcurrencyPolicy,
+  semaphore: Semaphore,
+  state: Mutex<OperationState>,
+  /// Label of the webview which started the current operation.
+  owner: Mutex<Option<String>>,
+  next_id: AtomicU64,
+}
+
+impl Operations {
+  pub fn new(policy: ConcurrencyPolicy) -> Self {
+    Operations {
+      policy,
+      semaphore: Semaphore::new(1),
+      state: Mutex::new(OperationState::Idle),
+      owner: Mutex::new(None),
+      next_id: AtomicU64::new(1),
+    }
+  }
+
+  /// Starts a new operation according to the policy.
+  /// `owner` is the label of the webview starting the operation, only it may answer and cancel the operation.
+  /// `cancel` is called to abort the current operation if the policy is [`ConcurrencyPolicy::Replace`].
+  /// The operation ends when the returned guard is dropped.
+  pub async fn begin(
+    &self,
+    kind: OperationKind,
+    owner: &str,
+    cancel: impl FnOnce(),
+  ) -> Result<Operation<'_>> {
+    let permit = match self.semaphore.try_acquire() {
+      Ok(permit) => permit,
+      Err(_) => match self.policy {
+        ConcurrencyPolicy::Reject => return Err(Error::OperationInProgress),
+        ConcurrencyPolicy::Queue => self.acquire().await?,
+        ConcurrencyPolicy::Replace => {
+          #[cfg(feature = "log")]
+          log::debug!("Cancelling {:?} to start a new operation", self.state());
+          cancel();
+          self.acquire().await?
+        }
+      },
+    };
+
+    let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+    #[cfg(feature = "log")]
+    log::debug!("Starting {kind:?} operation {id}");
+    *self.owner.lock().unwrap() = Some(owner.to_string());
+    self.set(OperationState::InProgress { id, kind });
+    Ok(Operation {
+      operations: self,
+      _permit: permit,
+    })
+  }
+
+  async fn acquire(&self) -> Result<SemaphorePermit<'_>> {
+    self.semaphore.acquire().await.map_err(|_| Error::Aborted)
+  }
+
+  /// The current state.
+  pub fn state(&self) -> OperationState {
+    *self.state.lock().unwrap()
+  }
+
+  /// Checks that the current operation, if any, was started by the webview `label`.
+  pub fn check_owner(&self, label: &str) -> Result<()> {
+    match &*self.owner.lock().unwrap() {
+      Some(owner) if owner != label => Err(Error::ForeignOperation),
+      _ => Ok(()),
+    }
+  }
+
+  /// The current operation waits for a PIN.
+  pub fn awaiting_pin(&self) {
+    self.update(|id, kind| OperationState::AwaitingPin { id, kind });
+  }
+
+  /// The current operation waits for the user to select a credential.
+  pub fn awaiting_selection(&self) {
+    self.update(|id, kind| OperationState::AwaitingSelection { id, kind });
+  }
+
+  /// The current operation continues after a PIN or selection was received.
+  pub fn resume(&self) {
+    self.update(|id, kind| OperationState::InProgress { id, kind });
+  }
+
+  fn update(&self, update: fn(u64, OperationKind) -> OperationState) {
+    let mut state = self.state.lock().unwrap();
+    *state = state.with(update);
+  }
+
+  fn set(&self, state: OperationState) {
+    *self.state.lock().unwrap() = state;
+  }
+}
+
+/// A running operation, resets the state to idle when dropped.
+pub struct Operation<'a> {
+  operations: &'a Operations,
+  _permit: SemaphorePermit<'a>,
+}
+
+impl Drop for Operation<'_> {
+  fn drop(&mut self) {
+    *self.operations.owner.lock().unwrap() = None;
+    self.operations.set(OperationState::Idle);
+  }
+}
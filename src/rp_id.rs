@@ -0,0 +1,150 @@
+use std::sync::OnceLock;
+
+use publicsuffix::{List, Psl};
+use tauri::Url;
+use url::Host;
+
+use crate::{Error, Result};
+
+/// Snapshot of <https://publicsuffix.org/list/public_suffix_list.dat>
+static PUBLIC_SUFFIX_LIST: &[u8] = include_bytes!("../psl/public_suffix_list.dat");
+
+fn public_suffix_list() -> &'static List {
+  static LIST: OnceLock<List> = OnceLock::new();
+  LIST.get_or_init(|| List::from_bytes(PUBLIC_SUFFIX_LIST).expect("bundled public suffix list"))
+}
+
+/// Checks that `origin` is a secure context and that `rp_id` is a registrable domain suffix of or equal to the origin's host.
+/// <https://html.spec.whatwg.org/multipage/browsers.html#is-a-registrable-domain-suffix-of-or-is-equal-to>
+pub fn validate(origin: &Url, rp_id: &str) -> Result<()> {
+  let Some(Host::Domain(origin_host)) = origin.host() else {
+    return Err(Error::InsecureOrigin(origin.clone()));
+  };
+  let is_localhost = origin_host == "localhost" || origin_host.ends_with(".localhost");
+  if origin.scheme() != "https" && !(origin.scheme() == "http" && is_localhost) {
+    return Err(Error::InsecureOrigin(origin.clone()));
+  }
+
+  let invalid = || Error::InvalidRpId {
+    origin: origin.clone(),
+    rp_id: rp_id.to_string(),
+  };
+
+  let Ok(Host::Domain(rp_id_host)) = Host::parse(rp_id) else {
+    return Err(invalid());
+  };
+  if rp_id_host == origin_host {
+    return Ok(());
+  }
+
+  if !origin_host.ends_with(&format!(".{rp_id_host}")) {
+    return Err(invalid());
+  }
+
+  let list = public_suffix_list();
+  let is_public_suffix = list
+    .suffix(rp_id_host.as_bytes())
+    .is_some_and(|suffix| suffix.as_bytes() == rp_id_host.as_bytes());
+  let origin_suffix_matches = list.suffix(origin_host.as_bytes()).is_some_and(|suffix| {
+    suffix
+      .as_bytes()
+      .ends_with(format!(".{rp_id_host}").as_bytes())
+  });
+  if is_public_suffix || origin_suffix_matches {
+    return Err(invalid());
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn check(origin: &str, rp_id: &str) -> Result<()> {
+    validate(&Url::parse(origin).unwrap(), rp_id)
+  }
+
+  #[test]
+  fn accepts_own_and_parent_domain() {
+    assert!(check("https://example.com", "example.com").is_ok());
+    assert!(check("https://login.example.com", "example.com").is_ok());
+    assert!(check("https://a.b.example.co.uk", "b.example.co.uk").is_ok());
+  }
+
+  #[test]
+  fn rejects_public_suffix() {
+    assert!(matches!(
+      check("https://example.co.uk", "co.uk"),
+      Err(Error::InvalidRpId { .. })
+    ));
+    assert!(matches!(
+      check("https://example.com", "com"),
+      Err(Error::InvalidRpId { .. })
+    ));
+    // github.io is on the private section of the list
+    assert!(matches!(
+      check("https://user.github.io", "github.io"),
+      Err(Error::InvalidRpId { .. })
+    ));
+  }
+
+  #[test]
+  fn rejects_sibling_and_child_domains() {
+    assert!(matches!(
+      check("https://login.example.com", "other.example.com"),
+      Err(Error::InvalidRpId { .. })
+    ));
+    assert!(matches!(
+      check("https://example.com", "login.example.com"),
+      Err(Error::InvalidRpId { .. })
+    ));
+    assert!(matches!(
+      check("https://notexample.com", "example.com"),
+      Err(Error::InvalidRpId { .. })
+    ));
+  }
+
+  #[test]
+  fn rejects_ip_addresses() {
+    assert!(matches!(
+      check("https://127.0.0.1", "127.0.0.1"),
+      Err(Error::InsecureOrigin(_))
+    ));
+    assert!(matches!(
+      check("https://[::1]", "[::1]"),
+      Err(Error::InsecureOrigin(_))
+    ));
+    assert!(matches!(
+      check("https://example.com", "127.0.0.1"),
+      Err(Error::InvalidRpId { .. })
+    ));
+  }
+
+  #[test]
+  fn allows_http_only_for_localhost() {
+    assert!(check("http://localhost:1420", "localhost").is_ok());
+    assert!(check("http://app.localhost", "app.localhost").is_ok());
+    assert!(matches!(
+      check("http://example.com", "example.com"),
+      Err(Error::InsecureOrigin(_))
+    ));
+    assert!(matches!(
+      check("tauri://localhost", "localhost"),
+      Err(Error::InsecureOrigin(_))
+    ));
+  }
+
+  #[test]
+  fn normalizes_case_but_not_trailing_dots() {
+    assert!(check("https://Login.Example.com", "EXAMPLE.com").is_ok());
+    assert!(matches!(
+      check("https://example.com", "example.com."),
+      Err(Error::InvalidRpId { .. })
+    ));
+    assert!(matches!(
+      check("https://example.com.", "example.com"),
+      Err(Error::InvalidRpId { .. })
+    ));
+  }
+}